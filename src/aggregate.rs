@@ -0,0 +1,150 @@
+use anyhow::{bail, Result};
+use std::fs;
+
+use crate::cli::{Options, Resolve};
+use crate::error::LabtohubError;
+use crate::output;
+use crate::{clean_worktree, commit_worktree, run_git_in, run_output, worktree_dir};
+
+/// Remote-tracking refs of the extra `--source-branch`es, in the order they are merged.
+pub fn extra_source_refs(options: &Options) -> Vec<String> {
+   options
+      .extra_source_branches
+      .iter()
+      .map(|branch| format!("{}/{}", options.source_remote, branch))
+      .collect()
+}
+
+/// `tree` with each of `paths` taken from `side` instead (or removed where `side` lacks it),
+/// built in a throwaway index so neither the repository's nor the worktree's is touched.
+fn resolve_conflicts(tree: &str, paths: &[String], side: &str) -> Result<String> {
+   let index = std::env::temp_dir().join(format!("labtohub-index-{}", std::process::id()));
+   let index_env = index.to_string_lossy().to_string();
+   let git = |args: &[&str]| -> Result<String> {
+      let output = output::git_command()
+         .args(args)
+         .env("GIT_INDEX_FILE", &index_env)
+         .output()?;
+      if !output.status.success() {
+         bail!("Command failed: git {}", args.join(" "));
+      }
+      Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+   };
+   let result = (|| {
+      git(&["read-tree", tree])?;
+      for path in paths {
+         let entry = run_output("git", &["ls-tree", side, "--", path])?;
+         match entry.split_once('\t') {
+            Some((meta, _)) => {
+               let mut fields = meta.split_whitespace();
+               let (Some(mode), Some(_), Some(oid)) = (fields.next(), fields.next(), fields.next()) else {
+                  bail!("Unexpected ls-tree output for '{}'", path);
+               };
+               git(&["update-index", "--add", "--cacheinfo", &format!("{},{},{}", mode, oid, path)])?;
+            }
+            None => {
+               git(&["update-index", "--force-remove", "--", path])?;
+            }
+         }
+      }
+      git(&["write-tree"])
+   })();
+   let _ = fs::remove_file(&index);
+   result
+}
+
+/// The tree of each content commit after the first: origin's branches merged so far plus
+/// the next `--source-branch`. Conflicts abort unless `--merge-strategy-option` picks a side,
+/// `ours` meaning the branches merged so far and `theirs` the one being added.
+pub fn merged_trees(options: &Options) -> Result<Vec<(String, String)>> {
+   let mut merged = run_output("git", &["rev-parse", &options.source_ref()])?;
+   let mut trees = Vec::new();
+   for reference in extra_source_refs(options) {
+      let output = output::git_command()
+         .args(["merge-tree", "--write-tree", "-z", "--name-only", "--no-messages"])
+         .args([&merged, &reference])
+         .output()?;
+      let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+      let mut fields = stdout.split('\0').filter(|field| !field.is_empty());
+      let tree = fields.next().unwrap_or_default().to_string();
+      let mut conflicted = fields.map(|path| path.to_string()).collect::<Vec<_>>();
+      conflicted.sort();
+      conflicted.dedup();
+      let tree = match output.status.code() {
+         Some(0) => tree,
+         Some(1) => match options.merge_strategy_option {
+            Some(resolve) => {
+               let (side, label) = match resolve {
+                  Resolve::Ours => (merged.as_str(), "the branches merged so far"),
+                  Resolve::Theirs => (reference.as_str(), reference.as_str()),
+               };
+               say!(
+                  "Resolving {} conflicting path(s) from {} in favour of {} (--merge-strategy-option {}).",
+                  conflicted.len(),
+                  reference,
+                  label,
+                  resolve.as_str()
+               );
+               resolve_conflicts(&tree, &conflicted, side)?
+            }
+            None => {
+               say!("Conflicts merging {} with the other source branches:", reference);
+               for path in &conflicted {
+                  say!("  {}", path);
+               }
+               return Err(LabtohubError::MergeConflict { paths: conflicted }.into());
+            }
+         },
+         _ => bail!(
+            "Could not merge {}: {}",
+            reference,
+            String::from_utf8_lossy(&output.stderr).trim()
+         ),
+      };
+      // An unreferenced commit, only so the next branch merges against the right base.
+      merged = run_output(
+         "git",
+         &["commit-tree", &tree, "-p", &merged, "-p", &reference, "-m", "labtohub: merged sources"],
+      )?;
+      trees.push((reference, tree));
+   }
+   Ok(trees)
+}
+
+fn replace_worktree_with(options: &Options, tree: &str) -> Result<()> {
+   run_git_in(
+      worktree_dir(),
+      &["restore", "--source", tree, "--staged", "--worktree", "."],
+   )?;
+   clean_worktree(options, &["."])
+}
+
+/// The content commit message for one extra source branch: the merge message with the
+/// branch it brings in added to the subject.
+fn source_message(message: &str, reference: &str) -> String {
+   match message.split_once('\n') {
+      Some((subject, body)) => format!("{} ({})\n{}", subject, reference, body),
+      None => format!("{} ({})", message, reference),
+   }
+}
+
+/// Commits each extra `--source-branch` onto the content branch, one commit per branch.
+/// Returns whether any of them changed something.
+pub fn commit_extra_sources(options: &Options, message: &str, date: Option<&str>) -> Result<bool> {
+   let mut committed = false;
+   for (reference, tree) in merged_trees(options)? {
+      say!("Adding {} to the worktree...", reference);
+      replace_worktree_with(options, &tree)?;
+      committed |= commit_worktree(options, &source_message(message, &reference), date)?;
+   }
+   Ok(committed)
+}
+
+/// For `--dry-run`: puts the fully merged sources in the worktree, so the staged preview
+/// covers every `--source-branch`.
+pub fn preview_extra_sources(options: &Options) -> Result<()> {
+   if let Some((_, tree)) = merged_trees(options)?.last() {
+      replace_worktree_with(options, tree)?;
+   }
+   Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::{bail, Result};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::cli::Options;
+use crate::hooks::HOOKS_DIR;
+use crate::{run, run_output};
+
+const STASH_MESSAGE: &str = "labtohub --no-worktree";
+
+/// The main checkout's top directory once `--no-worktree` has taken it over.
+static DIR: OnceLock<String> = OnceLock::new();
+
+/// Where the sync builds under `--no-worktree`; `None` when it uses the temporary worktree.
+pub fn dir() -> Option<&'static str> {
+   DIR.get().map(String::as_str)
+}
+
+/// What the checkout was on before the run took it over.
+pub struct Saved {
+   /// The branch that was checked out, `None` when HEAD was detached.
+   branch: Option<String>,
+   head: String,
+   stashed: bool,
+}
+
+/// `--no-worktree`: readies the main checkout for the sync. Everything git does not already
+/// have at HEAD, ignored files included, goes into a stash so the build starts from a clean
+/// tree and `restore` can bring it all back.
+pub fn save(options: &Options) -> Result<Saved> {
+   if !options.no_hooks && Path::new(HOOKS_DIR).is_dir() {
+      bail!("--no-worktree switches the checkout away from {}, so it needs --no-hooks", HOOKS_DIR);
+   }
+   let Ok(head) = run_output("git", &["rev-parse", "--verify", "--quiet", "HEAD^{commit}"]) else {
+      bail!("--no-worktree needs a checkout with at least one commit to return to");
+   };
+   for state in ["MERGE_HEAD", "CHERRY_PICK_HEAD", "REVERT_HEAD", "rebase-merge", "rebase-apply"] {
+      let path = run_output("git", &["rev-parse", "--git-path", state])?;
+      if Path::new(&path).exists() {
+         bail!("The checkout is in the middle of a merge, rebase or cherry-pick; finish or abort it before --no-worktree");
+      }
+   }
+   if !run_output("git", &["ls-files", "--unmerged"])?.is_empty() {
+      bail!("The checkout has unmerged paths; resolve them before --no-worktree");
+   }
+   let branch = run_output("git", &["symbolic-ref", "--short", "--quiet", "HEAD"]).ok();
+   let status = run_output("git", &["status", "--porcelain", "--untracked-files=all", "--ignored"])?;
+   let stashed = !status.is_empty();
+   if stashed {
+      say!("Stashing the checkout's changes and untracked files (--no-worktree)...");
+      run("git", &["stash", "push", "--all", "--quiet", "-m", STASH_MESSAGE])?;
+   }
+   let top = run_output("git", &["rev-parse", "--show-toplevel"])?;
+   let _ = DIR.set(top);
+   Ok(Saved { branch, head, stashed })
+}
+
+/// Puts the checkout back on the branch it was on and pops the stash `save` made. Runs from
+/// `Cleanup`, so it cannot fail the run; a step that fails says how to finish by hand.
+pub fn restore(saved: &Saved) {
+   let Some(top) = dir() else {
+      return;
+   };
+   say!("Restoring the checkout (--no-worktree)...");
+   let back = match &saved.branch {
+      Some(branch) => vec!["checkout", "--quiet", branch.as_str()],
+      None => vec!["checkout", "--quiet", "--detach", saved.head.as_str()],
+   };
+   // Whatever the build left is its own: the user's files are all in the stash.
+   let mut steps = vec![
+      vec!["reset", "--hard", "--quiet"],
+      vec!["clean", "-fdxq", "--", "."],
+      back,
+   ];
+   if saved.stashed {
+      steps.push(vec!["stash", "pop", "--index", "--quiet"]);
+   }
+   for step in steps {
+      let status = Command::new("git").arg("-C").arg(top).args(&step).status();
+      if !status.is_ok_and(|status| status.success()) {
+         let target = saved.branch.as_deref().unwrap_or(&saved.head);
+         say!(
+            "Warning: could not restore the checkout (`git {}` failed). Check out {} again{}.",
+            step.join(" "),
+            target,
+            if saved.stashed {
+               format!(" and run `git stash pop --index` for the stash named '{}'", STASH_MESSAGE)
+            } else {
+               String::new()
+            }
+         );
+         return;
+      }
+   }
+}
@@ -0,0 +1,1102 @@
+use anyhow::{bail, Result};
+
+pub const USAGE: &str = "Usage: labtohub [OPTIONS] [MESSAGE...]
+       labtohub diff [--stat] [--color[=<WHEN>]] [--no-pager] [OPTIONS]
+       labtohub validate [OPTIONS]
+       labtohub worktrees [--prune]
+
+Commands:
+  diff                        Show the patch a sync would apply to github, without a worktree
+  validate                    Check git, the repository, remotes and config without fetching or pushing
+  worktrees                   List the clone's worktrees, marking the ones labtohub left behind
+
+Options:
+  -m, --message <MSG>         Merge message (otherwise taken from the words, piped stdin or a prompt)
+  -F, --file <PATH>           Read the merge message from PATH ('-' for stdin)
+      --message-from-origin-log
+                              Generate the message from the origin commits new since the last sync
+      --allow-empty-message   Sync an empty message as 'labtohub sync' instead of refusing it
+      --log-summary-limit <N> List at most N commits in the generated message, ending `…and M more`
+      --log-format <list|oneline>
+                              List the generated message's commits one per line (default) or on one line
+      --describe-source       Add a `Source-Describe:` trailer with `git describe --tags` of the origin tip
+      --trailer <KEY=VALUE>   Add a `KEY: VALUE` trailer to the commit message; repeat it for more
+      --max-subject-length <N>
+                              Warn when the message's subject is longer than N characters
+      --strict-message        Refuse a subject over --max-subject-length instead of warning
+      --wrap-body <COLS>      Hard-wrap the message body's long lines at COLS columns
+      --source-remote <REMOTE>
+                              Remote to copy from (default: origin)
+      --dest-remote <REMOTE>  Remote to publish to (default: github)
+      --source-url <URL>      Copy from URL, through a remote added for this run only
+      --dest-url <URL>        Publish to URL, through a remote added for this run only
+      --source-branch <BRANCH>
+                              Branch on the source remote to copy from (default: main); repeat it to merge
+                              further branches in, each as a commit of its own
+      --dest-branch <BRANCH>  Branch on the destination remote to publish to (default: main)
+      --source-ref <TAG>      Copy origin's tag TAG instead of the source branch
+      --dest-branch-per-tag[=<PREFIX>]
+                              With --source-ref, publish to github branch <PREFIX><TAG> (default: release/)
+      --dest-default-branch auto
+                              Publish to the destination remote's default branch (main if unknown)
+      --dest-ref <REF>        Fully-qualified ref to push to instead of refs/heads/<dest-branch>
+      --branch <NAME>         Name the content branch NAME instead of deriving it from the message
+      --no-auto-slug          Never derive the content branch from the message; require --branch
+      --staging-prefix <P>    Prefix for the staging branch, named <P><dest-branch> (default: labtohub-)
+      --dest-subdir <PATH>    Publish origin's tree under PATH on github, leaving the rest untouched
+      --strip-prefix <PATH>   Publish origin's PATH directory at github's root, keeping the files beside it
+      --vendor-submodules     Replace origin's submodules with their files, dropping .gitmodules
+      --include <PATHSPEC>    Only copy paths matching PATHSPEC from origin, leaving the rest as github has
+                              it (repeatable)
+      --clean-ignored         Also remove gitignored files from the worktree (git clean -fdx)
+      --no-clean              Skip git clean, keeping untracked files in the worktree
+      --merge-path <GLOB>     Three-way merge matching files instead of overwriting them (repeatable)
+      --merge-path-resolve <ours|theirs>
+                              Resolve --merge-path conflicts in favour of github (ours) or origin (theirs)
+      --merge-strategy-option <ours|theirs>
+                              Resolve --merge-path and several-branch conflicts (needs either)
+      --since-last-sync       Apply only origin's changes since the last recorded sync instead of overwriting
+      --replay                Replay each new origin commit with its message and author instead of one commit
+      --create-dest-branch    Create the github branch from origin when github has other branches but not it
+      --set-dest-head         When a sync creates the github branch, make it the repository's default (GH_TOKEN)
+      --set-status <STATE>    After pushing, post a labtohub/sync commit status (e.g. success) on the result
+      --orphan-each-sync      Replace the github branch with a single parentless commit of origin's tree (force-push)
+      --base-ref <REV>        Build on this github commit instead of the github branch's tip (force-push)
+      --since-tag <TAG>       Apply only origin's changes since the release TAG instead of overwriting
+      --diff-filter <AMD>     Only sync the selected kinds of change: Added, Modified, Deleted files
+      --on-no-change <CODE>   Exit with CODE when there is nothing to publish (default: 0)
+      --commit-empty          Commit and push the message even when nothing changed
+      --max-file-size <SIZE>  Warn about staged files larger than SIZE, e.g. 50M or 2G (default: 50M)
+      --reject-large-files    Abort instead of warning when files exceed --max-file-size
+      --refresh-index         Refresh git's stat cache before staging, for network or slow filesystems
+      --depth <N>             Fetch only the last N commits of each branch (fetches more when needed)
+      --shallow-since <DATE>  Fetch only origin's commits after DATE (fetches more when needed)
+      --single-branch         Fetch only the named branches into their remote-tracking refs, without tags
+      --scan-secrets          Abort if the staged changes add likely secrets (keys, tokens)
+      --allow-secrets         With --scan-secrets, only report findings instead of aborting
+      --no-fetch              Use the remote-tracking refs as they are instead of fetching
+      --fetch-refspec <REMOTE>=<REFSPEC>
+                              Fetch an extra refspec from either remote (repeatable)
+      --verify-signatures     Refuse to sync unless the source tip has a good GPG or SSH signature
+      --allowed-signers <PATH>
+                              With --verify-signatures, trust the SSH keys in PATH (gpg.ssh.allowedSignersFile)
+      --abort-if-behind       Abort if github has commits whose content origin never had
+      --expected-dest-sha <SHA>
+                              Fail unless the github branch is at SHA, and push with a lease on it
+      --push-option <OPTION>  Pass a server-side push option such as ci.skip to the push as -o (repeatable)
+      --git-dir <PATH>        Use the repository at PATH for the main clone (separate git dir or bare repo)
+      --ssh-command <CMD>     Run git's SSH connections through CMD (GIT_SSH_COMMAND), e.g. a deploy key
+      --throttle <N>          Make at most N fetches, pushes and other remote calls a minute
+      --resume                If an earlier run committed but failed to push, push that commit again
+                              instead of rebuilding, as long as origin, github and the message are unchanged
+      --retry-push-on-reject <N>
+                              If the github branch moved before our push, re-fetch, rebuild and retry up to N times
+      --tag <NAME>            Create an annotated tag NAME on the result and push it too
+      --sign                  Sign the content commit and any --tag (GPG or SSH, per gpg.format)
+      --tag-message-template <TEMPLATE>
+                              Tag message, with {message}, {subject}, {branch}, {source_ref}, {dest_ref},
+                              {source_sha} and {date} filled in (default: the merge message)
+      --github-release        After pushing the --tag, create a GitHub release for it (token from GH_TOKEN)
+      --prerelease            Mark the --github-release as a pre-release
+      --draft                 Create the --github-release as a draft
+      --push-compare          Push the content branch to github on its own and print a compare URL, leaving
+                              the github branch untouched
+      --verify-clean-after    After pushing, re-fetch and fail unless the github branch matches the source
+      --verify-worktree-empty Before copying origin in, fail unless the worktree is exactly the github branch
+      --dry-run               Build and stage the worktree and show what would change; commit and push nothing
+      --build-only            Commit the content branch and stop, leaving the worktree; merge and push nothing
+      --verify-command <CMD>  Run CMD in the worktree after the content commit; abort the sync if it fails
+      --check-remotes         Check both remotes are reachable and have the branches (alone or with --dry-run)
+      --keep-staging-branch   Preserve the staging branch after the run and print its name for inspection
+      --tmp-cleanup-on-start-only
+                              Leave the worktree behind after the run; it is still removed when the next run starts
+      --no-worktree           Build in this checkout instead of a worktree, stashing and restoring its state
+      --wait-for-lock <SECS>  If another run holds the repository's lock, wait up to SECS for it (default: 0)
+      --force-worktree        Replace a worktree at .labtohub-tmp even if labtohub did not create it
+      --no-hooks              Do not run scripts from .labtohub/hooks/
+      --quiet-git             Pass --quiet to git and turn off its hints
+      --progress <lines|oneline>
+                              Progress style: a line per step, or one status line redrawn in place (default: lines)
+      --format <text|json>    Print a JSON summary on stdout (progress moves to stderr)
+      --summary-only          Print only a one-line `result=... changed=... sha=... branch=...` summary on stdout
+      --report-file <PATH>    Write a report of the run to PATH, also when it fails (JSON with --format json,
+                              Markdown otherwise)
+      --notify-on-failure-url <URL>
+                              POST a JSON summary of a failed run (phase, error, settings) to URL
+      --changed-paths-file <PATH>
+                              Write the paths the sync changed to PATH, one per line (empty when nothing changed)
+      --confirm-diff-threshold <N>
+                              Skip the final prompt when fewer than N files would change
+      --confirm-before-push   Show what the built result changes on github and ask once more before pushing
+      --pause                 Wait for Enter after each step of the build, to inspect .labtohub-tmp
+      --stat                  diff: show a diffstat instead of the patch
+      --context <N>           diff: show N lines of context around each change (default: git's 3)
+      --ignore-whitespace     Treat whitespace-only differences as none in `diff` and the up-to-date check
+      --find-renames[=<N>]    Count and show moved files as renames (similarity N, default 50%)
+      --color[=<WHEN>]        diff: colour the output (always, never or auto)
+      --no-pager              diff: do not page the output
+      --prune                 worktrees: remove the labtohub worktrees, leaving the others alone
+      --profile <NAME>        Layer the [profile.NAME] section of .labtohub.toml over its top-level keys
+      --no-<SWITCH>           Turn off a switch set earlier or in config (--hooks undoes --no-hooks)
+  -y, --yes                   Skip confirmation prompts (warnings are still printed)
+  -h, --help                  Print this help";
+
+pub const DEFAULT_SOURCE_REMOTE: &str = "origin";
+pub const DEFAULT_DEST_REMOTE: &str = "github";
+pub const DEFAULT_SOURCE_BRANCH: &str = "main";
+pub const DEFAULT_DEST_BRANCH: &str = "main";
+pub const DEFAULT_STAGING_PREFIX: &str = "labtohub-";
+pub const DEFAULT_TAG_BRANCH_PREFIX: &str = "release/";
+/// GitHub starts warning about files at 50 MB.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolve {
+   Ours,
+   Theirs,
+}
+
+impl Resolve {
+   /// The name git uses for this side, as in `-X ours` or `merge-file --ours`.
+   pub fn as_str(self) -> &'static str {
+      match self {
+         Resolve::Ours => "ours",
+         Resolve::Theirs => "theirs",
+      }
+   }
+}
+
+/// How `--message-from-origin-log` lists the new commits in the message body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+   /// One `- <sha> <subject>` line per commit.
+   List,
+   /// The subjects on a single line, separated by `; `.
+   Oneline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+   Text,
+   Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+   Lines,
+   Oneline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subcommand {
+   Sync,
+   Diff,
+   Validate,
+   Worktrees,
+}
+
+#[derive(Debug)]
+pub struct Options {
+   pub subcommand: Subcommand,
+   pub message: Option<String>,
+   pub message_file: Option<String>,
+   pub message_from_origin_log: bool,
+   pub allow_empty_message: bool,
+   pub log_summary_limit: Option<usize>,
+   pub log_format: LogFormat,
+   pub describe_source: bool,
+   /// `--trailer` values as `Key: value`, in the order given.
+   pub trailers: Vec<String>,
+   pub max_subject_length: Option<usize>,
+   pub strict_message: bool,
+   pub wrap_body: Option<usize>,
+   pub source_remote: String,
+   pub dest_remote: String,
+   pub source_url: Option<String>,
+   pub dest_url: Option<String>,
+   pub source_branch: String,
+   pub extra_source_branches: Vec<String>,
+   /// `--source-ref`: a tag on the source remote to copy instead of the source branch.
+   pub source_tag: Option<String>,
+   /// `--dest-branch-per-tag`: the prefix the destination branch is named with, before the tag.
+   pub dest_branch_per_tag: Option<String>,
+   pub dest_branch: String,
+   /// `--dest-default-branch auto`: use the destination remote's default branch instead.
+   pub detect_dest_branch: bool,
+   pub dest_push_ref: Option<String>,
+   pub branch: Option<String>,
+   pub no_auto_slug: bool,
+   pub staging_prefix: String,
+   pub scan_secrets: bool,
+   pub allow_secrets: bool,
+   pub no_fetch: bool,
+   pub depth: Option<u32>,
+   /// `--shallow-since`: only fetch the source's history after this date.
+   pub shallow_since: Option<String>,
+   pub single_branch: bool,
+   pub fetch_refspecs: Vec<(String, String)>,
+   pub dest_subdir: Option<String>,
+   /// `--strip-prefix`: the origin directory whose contents are published at the root.
+   pub strip_prefix: Option<String>,
+   pub includes: Vec<String>,
+   pub clean_ignored: bool,
+   pub no_clean: bool,
+   pub merge_paths: Vec<String>,
+   pub merge_path_resolve: Option<Resolve>,
+   pub merge_strategy_option: Option<Resolve>,
+   pub since_last_sync: bool,
+   /// `--replay`: carry origin's commits over one by one instead of as one commit.
+   pub replay: bool,
+   pub since_tag: Option<String>,
+   pub diff_filter: Option<String>,
+   pub orphan_each_sync: bool,
+   /// `--base-ref`: what the worktree is built on instead of the destination branch.
+   pub base_ref: Option<String>,
+   pub create_dest_branch: bool,
+   pub set_dest_head: bool,
+   pub set_status: Option<String>,
+   pub on_no_change: u8,
+   pub commit_empty: bool,
+   pub refresh_index: bool,
+   pub max_file_size: u64,
+   pub reject_large_files: bool,
+   pub verify_signatures: bool,
+   pub allowed_signers: Option<String>,
+   pub abort_if_behind: bool,
+   pub expected_dest_sha: Option<String>,
+   pub push_options: Vec<String>,
+   pub ssh_command: Option<String>,
+   /// `--git-dir`: the main clone's repository, for a clone laid out without a `.git` here.
+   pub git_dir: Option<String>,
+   pub throttle: Option<u32>,
+   pub resume: bool,
+   pub retry_push_on_reject: u32,
+   pub tag: Option<String>,
+   pub sign: bool,
+   pub tag_message_template: Option<String>,
+   pub github_release: bool,
+   pub prerelease: bool,
+   pub draft: bool,
+   pub push_compare: bool,
+   pub verify_clean_after: bool,
+   pub verify_worktree_empty: bool,
+   pub dry_run: bool,
+   pub build_only: bool,
+   /// `--verify-command`: a shell command that must pass in the worktree before the push.
+   pub verify_command: Option<String>,
+   pub vendor_submodules: bool,
+   pub check_remotes: bool,
+   pub keep_staging_branch: bool,
+   pub tmp_cleanup_on_start_only: bool,
+   pub no_worktree: bool,
+   pub wait_for_lock: u64,
+   pub force_worktree: bool,
+   pub no_hooks: bool,
+   pub quiet_git: bool,
+   pub progress: Progress,
+   pub format: Format,
+   pub summary_only: bool,
+   pub report_file: Option<String>,
+   pub notify_on_failure_url: Option<String>,
+   /// `--profile`: the `[profile.<name>]` section of the config file to layer over its top level.
+   pub profile: Option<String>,
+   pub changed_paths_file: Option<String>,
+   pub confirm_diff_threshold: Option<usize>,
+   pub confirm_before_push: bool,
+   pub pause: bool,
+   pub diff_stat: bool,
+   /// `--context`: lines of context around each change in `labtohub diff`'s patch.
+   pub diff_context: Option<u32>,
+   pub ignore_whitespace: bool,
+   /// `--find-renames`: the similarity threshold git's rename detection is run with.
+   pub find_renames: Option<String>,
+   pub prune_worktrees: bool,
+   pub diff_color: Option<String>,
+   pub no_pager: bool,
+   pub yes: bool,
+   pub help: bool,
+}
+
+impl Default for Options {
+   fn default() -> Self {
+      Options {
+         subcommand: Subcommand::Sync,
+         message: None,
+         message_file: None,
+         message_from_origin_log: false,
+         allow_empty_message: false,
+         log_summary_limit: None,
+         log_format: LogFormat::List,
+         describe_source: false,
+         trailers: Vec::new(),
+         max_subject_length: None,
+         strict_message: false,
+         wrap_body: None,
+         source_remote: DEFAULT_SOURCE_REMOTE.to_string(),
+         dest_remote: DEFAULT_DEST_REMOTE.to_string(),
+         source_url: None,
+         dest_url: None,
+         source_branch: DEFAULT_SOURCE_BRANCH.to_string(),
+         extra_source_branches: Vec::new(),
+         source_tag: None,
+         dest_branch_per_tag: None,
+         dest_branch: DEFAULT_DEST_BRANCH.to_string(),
+         detect_dest_branch: false,
+         dest_push_ref: None,
+         branch: None,
+         no_auto_slug: false,
+         staging_prefix: DEFAULT_STAGING_PREFIX.to_string(),
+         scan_secrets: false,
+         allow_secrets: false,
+         no_fetch: false,
+         depth: None,
+         shallow_since: None,
+         single_branch: false,
+         fetch_refspecs: Vec::new(),
+         dest_subdir: None,
+         strip_prefix: None,
+         includes: Vec::new(),
+         clean_ignored: false,
+         no_clean: false,
+         merge_paths: Vec::new(),
+         merge_path_resolve: None,
+         merge_strategy_option: None,
+         since_last_sync: false,
+         replay: false,
+         since_tag: None,
+         diff_filter: None,
+         orphan_each_sync: false,
+         base_ref: None,
+         create_dest_branch: false,
+         set_dest_head: false,
+         set_status: None,
+         on_no_change: 0,
+         commit_empty: false,
+         refresh_index: false,
+         max_file_size: DEFAULT_MAX_FILE_SIZE,
+         reject_large_files: false,
+         verify_signatures: false,
+         allowed_signers: None,
+         abort_if_behind: false,
+         expected_dest_sha: None,
+         push_options: Vec::new(),
+         ssh_command: None,
+         git_dir: None,
+         throttle: None,
+         resume: false,
+         retry_push_on_reject: 0,
+         tag: None,
+         sign: false,
+         tag_message_template: None,
+         github_release: false,
+         prerelease: false,
+         draft: false,
+         push_compare: false,
+         verify_clean_after: false,
+         verify_worktree_empty: false,
+         dry_run: false,
+         build_only: false,
+         verify_command: None,
+         vendor_submodules: false,
+         check_remotes: false,
+         keep_staging_branch: false,
+         tmp_cleanup_on_start_only: false,
+         no_worktree: false,
+         wait_for_lock: 0,
+         force_worktree: false,
+         no_hooks: false,
+         quiet_git: false,
+         progress: Progress::Lines,
+         format: Format::Text,
+         summary_only: false,
+         report_file: None,
+         notify_on_failure_url: None,
+         profile: None,
+         changed_paths_file: None,
+         confirm_diff_threshold: None,
+         confirm_before_push: false,
+         pause: false,
+         diff_stat: false,
+         diff_context: None,
+         ignore_whitespace: false,
+         find_renames: None,
+         prune_worktrees: false,
+         diff_color: None,
+         no_pager: false,
+         yes: false,
+         help: false,
+      }
+   }
+}
+
+impl Options {
+   /// Remote-tracking ref of the branch we copy from, e.g. `origin/main`.
+   pub fn source_ref(&self) -> String {
+      match &self.source_tag {
+         Some(tag) => format!("{}/tags/{}", self.source_remote, tag),
+         None => format!("{}/{}", self.source_remote, self.source_branch),
+      }
+   }
+
+   /// What the worktree is built on: `--base-ref`, or else the destination branch.
+   pub fn worktree_base(&self) -> String {
+      self.base_ref.clone().unwrap_or_else(|| self.dest_ref())
+   }
+
+   /// The ref on the source remote that is copied: the branch, or the `--source-ref` tag.
+   pub fn source_remote_ref(&self) -> String {
+      match &self.source_tag {
+         Some(tag) => format!("refs/tags/{}", tag),
+         None => format!("refs/heads/{}", self.source_branch),
+      }
+   }
+
+   /// Remote-tracking ref of the branch we publish to, e.g. `github/main`.
+   pub fn dest_ref(&self) -> String {
+      format!("{}/{}", self.dest_remote, self.dest_branch)
+   }
+
+   /// What on github should match the source tree: the branch, or its `--dest-subdir`.
+   pub fn dest_tree(&self) -> String {
+      match &self.dest_subdir {
+         Some(subdir) => format!("{}:{}", self.dest_ref(), subdir),
+         None => self.dest_ref(),
+      }
+   }
+
+   /// Ref the staging branch is pushed to. Fully qualified so a same-named tag on the
+   /// destination can never be picked instead of the branch.
+   pub fn push_ref(&self) -> String {
+      match &self.dest_push_ref {
+         Some(push_ref) => push_ref.clone(),
+         None => format!("refs/heads/{}", self.dest_branch),
+      }
+   }
+
+   /// How the pushed ref is shown in progress output: `github/main`, or `github refs/...`
+   /// when `--dest-ref` overrides it.
+   pub fn push_target(&self) -> String {
+      match &self.dest_push_ref {
+         Some(push_ref) => format!("{} {}", self.dest_remote, push_ref),
+         None => self.dest_ref(),
+      }
+   }
+
+   /// `-o <option>` pairs for `git push`, one per `--push-option`.
+   pub fn push_option_args(&self) -> Vec<&str> {
+      self
+         .push_options
+         .iter()
+         .flat_map(|option| ["-o", option.as_str()])
+         .collect()
+   }
+
+   /// `--find-renames=<threshold>` for the diffs that count and list changes, when asked for.
+   pub fn rename_arg(&self) -> Option<String> {
+      self
+         .find_renames
+         .as_ref()
+         .map(|threshold| format!("--find-renames={}", threshold))
+   }
+
+   /// Dry runs change nothing, so they never need to ask before continuing.
+   pub fn skip_confirmations(&self) -> bool {
+      self.yes || self.dry_run
+   }
+
+   /// Local branch the worktree builds the result on before pushing.
+   pub fn staging_branch(&self) -> String {
+      format!("{}{}", self.staging_prefix, self.dest_branch)
+   }
+}
+
+/// Switches that can be turned back off later in the arguments, so the command line can undo
+/// one the config file or environment set: `--no-<switch>`, or `--fetch` for `--no-fetch`.
+const SWITCHES: &[&str] = &[
+   "--message-from-origin-log", "--allow-empty-message", "--describe-source", "--strict-message",
+   "--no-auto-slug", "--clean-ignored", "--no-clean", "--since-last-sync", "--replay",
+   "--orphan-each-sync", "--create-dest-branch", "--set-dest-head", "--commit-empty",
+   "--refresh-index", "--reject-large-files", "--scan-secrets", "--allow-secrets", "--no-fetch",
+   "--single-branch", "--verify-signatures", "--abort-if-behind", "--resume", "--sign",
+   "--github-release", "--prerelease", "--draft", "--push-compare", "--verify-clean-after",
+   "--verify-worktree-empty", "--dry-run", "--build-only", "--vendor-submodules", "--check-remotes",
+   "--keep-staging-branch", "--tmp-cleanup-on-start-only", "--no-worktree", "--force-worktree",
+   "--no-hooks", "--quiet-git", "--summary-only", "--confirm-before-push", "--pause", "--stat",
+   "--ignore-whitespace", "--no-pager", "--prune", "--yes",
+];
+
+/// The switch `flag` turns off, if it is the negated form of one.
+fn negated_switch(flag: &str) -> Option<&'static str> {
+   SWITCHES.iter().copied().find(|switch| match switch.strip_prefix("--no-") {
+      Some(rest) => flag.strip_prefix("--") == Some(rest),
+      None => flag.strip_prefix("--no-") == switch.strip_prefix("--"),
+   })
+}
+
+fn take_value<'a>(
+   flag: &str,
+   inline: Option<&'a str>,
+   rest: &mut impl Iterator<Item = &'a String>,
+) -> Result<String> {
+   match inline {
+      Some(value) => Ok(value.to_string()),
+      None => match rest.next() {
+         Some(value) => Ok(value.clone()),
+         None => bail!("Option {} requires a value", flag),
+      },
+   }
+}
+
+/// Turns `--trailer Key=value` into the `Key: value` line git writes. The key becomes the
+/// trailer's token, so it cannot hold spaces or a colon.
+fn parse_trailer(value: &str) -> Result<String> {
+   let Some((key, text)) = value.split_once('=') else {
+      bail!("--trailer expects KEY=VALUE, got '{}'", value);
+   };
+   let key = key.trim();
+   if key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == ':') {
+      bail!("Invalid --trailer key '{}'; it cannot be empty or contain spaces or colons", key);
+   }
+   let text = text.trim();
+   if text.is_empty() || text.contains('\n') {
+      bail!("Invalid --trailer value for '{}'; it must be a single non-empty line", key);
+   }
+   Ok(format!("{}: {}", key, text))
+}
+
+/// Parses sizes like `1048576`, `500K`, `50M`, `50MB` or `2G` (binary units).
+fn parse_size(value: &str) -> Result<u64> {
+   let upper = value.trim().to_ascii_uppercase();
+   let digits = upper.trim_end_matches('B');
+   let (number, multiplier) = match digits.chars().last() {
+      Some('K') => (&digits[..digits.len() - 1], 1024),
+      Some('M') => (&digits[..digits.len() - 1], 1024 * 1024),
+      Some('G') => (&digits[..digits.len() - 1], 1024 * 1024 * 1024),
+      _ => (digits, 1),
+   };
+   match number.trim().parse::<u64>() {
+      Ok(number) => Ok(number * multiplier),
+      Err(_) => bail!("Invalid size '{}'; use e.g. 50M or 2G", value),
+   }
+}
+
+fn normalize_subdir(flag: &str, value: &str) -> Result<String> {
+   let subdir = value.trim().trim_matches('/');
+   if subdir.is_empty() || subdir.split('/').any(|part| part == ".." || part == ".") {
+      bail!("{} must be a relative path inside the repository, got '{}'", flag, value);
+   }
+   Ok(subdir.to_string())
+}
+
+pub fn parse_args(argv: &[String]) -> Result<Options> {
+   check_combinations(parse_tokens(argv)?)
+}
+
+/// `argv` read option by option, without `check_combinations`, for one layer of a run's
+/// arguments: an option may need another that a different layer gives.
+pub fn parse_tokens(argv: &[String]) -> Result<Options> {
+   let mut options = Options::default();
+   let mut words = Vec::new();
+   // The first --source-branch replaces the default; later ones are merged in after it.
+   let mut source_branch_given = false;
+   let mut rest = argv.iter();
+   while let Some(arg) = rest.next() {
+      let (flag, inline) = match arg.split_once('=') {
+         Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+         _ => (arg.as_str(), None),
+      };
+      let (flag, enable) = match negated_switch(flag) {
+         Some(switch) => (switch, false),
+         None => (flag, true),
+      };
+      match flag {
+         "-m" | "--message" => {
+            // A trailing `-m` without a value falls back to the prompt, as it always has.
+            if let Ok(message) = take_value(flag, inline, &mut rest) {
+               options.message = Some(message);
+            }
+         }
+         "-F" | "--file" => options.message_file = Some(take_value(flag, inline, &mut rest)?),
+         "--message-from-origin-log" => options.message_from_origin_log = enable,
+         "--allow-empty-message" => options.allow_empty_message = enable,
+         "--log-summary-limit" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.log_summary_limit = match value.parse() {
+               Ok(limit) if limit > 0 => Some(limit),
+               _ => bail!("--log-summary-limit expects a positive number of commits, got '{}'", value),
+            }
+         }
+         "--log-format" => {
+            options.log_format = match take_value(flag, inline, &mut rest)?.as_str() {
+               "list" => LogFormat::List,
+               "oneline" => LogFormat::Oneline,
+               other => bail!("--log-format expects 'list' or 'oneline', got '{}'", other),
+            }
+         }
+         "--describe-source" => options.describe_source = enable,
+         "--max-subject-length" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.max_subject_length = match value.parse() {
+               Ok(limit) if limit > 0 => Some(limit),
+               _ => bail!("--max-subject-length expects a positive number of characters, got '{}'", value),
+            }
+         }
+         "--strict-message" => options.strict_message = enable,
+         "--wrap-body" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.wrap_body = match value.parse() {
+               Ok(width) if width > 0 => Some(width),
+               _ => bail!("--wrap-body expects a positive number of columns, got '{}'", value),
+            }
+         }
+         "--trailer" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.trailers.push(parse_trailer(&value)?);
+         }
+         "--source-remote" => options.source_remote = take_value(flag, inline, &mut rest)?,
+         "--dest-remote" => options.dest_remote = take_value(flag, inline, &mut rest)?,
+         "--source-url" => options.source_url = Some(take_value(flag, inline, &mut rest)?),
+         "--dest-url" => options.dest_url = Some(take_value(flag, inline, &mut rest)?),
+         "--source-branch" => {
+            let branch = take_value(flag, inline, &mut rest)?;
+            if source_branch_given {
+               options.extra_source_branches.push(branch);
+            } else {
+               options.source_branch = branch;
+               source_branch_given = true;
+            }
+         }
+         "--dest-branch" => options.dest_branch = take_value(flag, inline, &mut rest)?,
+         "--source-ref" => options.source_tag = Some(take_value(flag, inline, &mut rest)?),
+         "--dest-branch-per-tag" => {
+            options.dest_branch_per_tag = Some(inline.unwrap_or(DEFAULT_TAG_BRANCH_PREFIX).to_string())
+         }
+         "--dest-default-branch" => match take_value(flag, inline, &mut rest)?.as_str() {
+            "auto" => options.detect_dest_branch = true,
+            other => bail!(
+               "--dest-default-branch expects 'auto', got '{}'; name a branch with --dest-branch instead",
+               other
+            ),
+         },
+         "--dest-ref" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            if !value.starts_with("refs/") {
+               bail!("--dest-ref expects a fully-qualified ref such as refs/heads/main, got '{}'", value);
+            }
+            options.dest_push_ref = Some(value);
+         }
+         "--branch" => options.branch = Some(take_value(flag, inline, &mut rest)?),
+         "--no-auto-slug" => options.no_auto_slug = enable,
+         "--staging-prefix" => options.staging_prefix = take_value(flag, inline, &mut rest)?,
+         "--dest-subdir" => {
+            options.dest_subdir = Some(normalize_subdir(flag, &take_value(flag, inline, &mut rest)?)?)
+         }
+         "--strip-prefix" => {
+            options.strip_prefix = Some(normalize_subdir(flag, &take_value(flag, inline, &mut rest)?)?)
+         }
+         "--include" => options.includes.push(take_value(flag, inline, &mut rest)?),
+         "--clean-ignored" => options.clean_ignored = enable,
+         "--no-clean" => options.no_clean = enable,
+         "--merge-path" => options.merge_paths.push(take_value(flag, inline, &mut rest)?),
+         "--merge-path-resolve" => {
+            options.merge_path_resolve = Some(match take_value(flag, inline, &mut rest)?.as_str() {
+               "ours" => Resolve::Ours,
+               "theirs" => Resolve::Theirs,
+               other => bail!("--merge-path-resolve expects 'ours' or 'theirs', got '{}'", other),
+            })
+         }
+         "--merge-strategy-option" => {
+            options.merge_strategy_option = Some(match take_value(flag, inline, &mut rest)?.as_str() {
+               "ours" => Resolve::Ours,
+               "theirs" => Resolve::Theirs,
+               other => bail!("--merge-strategy-option expects 'ours' or 'theirs', got '{}'", other),
+            })
+         }
+         "--since-last-sync" => options.since_last_sync = enable,
+         "--replay" => options.replay = enable,
+         "--since-tag" => options.since_tag = Some(take_value(flag, inline, &mut rest)?),
+         "--diff-filter" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            if value.is_empty() || !value.chars().all(|c| "AMD".contains(c)) {
+               bail!("--diff-filter expects a combination of A, M and D, e.g. AM, got '{}'", value);
+            }
+            options.diff_filter = Some(value);
+         }
+         "--orphan-each-sync" => options.orphan_each_sync = enable,
+         "--base-ref" => options.base_ref = Some(take_value(flag, inline, &mut rest)?),
+         "--create-dest-branch" => options.create_dest_branch = enable,
+         "--set-dest-head" => options.set_dest_head = enable,
+         "--set-status" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            if !["success", "pending", "failure", "error"].contains(&value.as_str()) {
+               bail!("--set-status expects success, pending, failure or error, got '{}'", value);
+            }
+            options.set_status = Some(value);
+         }
+         "--on-no-change" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.on_no_change = match value.parse() {
+               Ok(code) => code,
+               Err(_) => bail!("--on-no-change expects an exit code from 0 to 255, got '{}'", value),
+            }
+         }
+         "--commit-empty" => options.commit_empty = enable,
+         "--refresh-index" => options.refresh_index = enable,
+         "--max-file-size" => {
+            options.max_file_size = parse_size(&take_value(flag, inline, &mut rest)?)?
+         }
+         "--reject-large-files" => options.reject_large_files = enable,
+         "--scan-secrets" => options.scan_secrets = enable,
+         "--allow-secrets" => options.allow_secrets = enable,
+         "--no-fetch" => options.no_fetch = enable,
+         "--depth" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.depth = match value.parse() {
+               Ok(depth) if depth > 0 => Some(depth),
+               _ => bail!("--depth expects a positive number of commits, got '{}'", value),
+            }
+         }
+         "--shallow-since" => options.shallow_since = Some(take_value(flag, inline, &mut rest)?),
+         "--single-branch" => options.single_branch = enable,
+         "--fetch-refspec" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            match value.split_once('=') {
+               Some((remote, refspec)) if !remote.is_empty() && !refspec.is_empty() => options
+                  .fetch_refspecs
+                  .push((remote.to_string(), refspec.to_string())),
+               _ => bail!("--fetch-refspec expects <remote>=<refspec>, got '{}'", value),
+            }
+         }
+         "--verify-signatures" => options.verify_signatures = enable,
+         "--allowed-signers" => options.allowed_signers = Some(take_value(flag, inline, &mut rest)?),
+         "--abort-if-behind" => options.abort_if_behind = enable,
+         "--expected-dest-sha" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            if value.len() < 4 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+               bail!("--expected-dest-sha expects a commit SHA, got '{}'", value);
+            }
+            options.expected_dest_sha = Some(value);
+         }
+         "--push-option" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            if value.is_empty() || value.contains('\n') {
+               bail!("--push-option expects a single-line option such as ci.skip");
+            }
+            options.push_options.push(value);
+         }
+         "--ssh-command" => options.ssh_command = Some(take_value(flag, inline, &mut rest)?),
+         "--git-dir" => options.git_dir = Some(take_value(flag, inline, &mut rest)?),
+         "--resume" => options.resume = enable,
+         "--retry-push-on-reject" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.retry_push_on_reject = match value.parse() {
+               Ok(retries) => retries,
+               Err(_) => bail!("--retry-push-on-reject expects a number of retries, got '{}'", value),
+            }
+         }
+         "--tag" => options.tag = Some(take_value(flag, inline, &mut rest)?),
+         "--sign" => options.sign = enable,
+         "--tag-message-template" => {
+            options.tag_message_template = Some(take_value(flag, inline, &mut rest)?)
+         }
+         "--github-release" => options.github_release = enable,
+         "--prerelease" => options.prerelease = enable,
+         "--draft" => options.draft = enable,
+         "--push-compare" => options.push_compare = enable,
+         "--verify-clean-after" => options.verify_clean_after = enable,
+         "--verify-worktree-empty" => options.verify_worktree_empty = enable,
+         "--dry-run" => options.dry_run = enable,
+         "--build-only" => options.build_only = enable,
+         "--verify-command" => options.verify_command = Some(take_value(flag, inline, &mut rest)?),
+         "--vendor-submodules" => options.vendor_submodules = enable,
+         "--check-remotes" => options.check_remotes = enable,
+         "--keep-staging-branch" => options.keep_staging_branch = enable,
+         "--tmp-cleanup-on-start-only" => options.tmp_cleanup_on_start_only = enable,
+         "--no-worktree" => options.no_worktree = enable,
+         "--throttle" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.throttle = match value.parse() {
+               Ok(per_minute) if per_minute > 0 => Some(per_minute),
+               _ => bail!("--throttle expects a positive number of operations per minute, got '{}'", value),
+            }
+         }
+         "--wait-for-lock" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.wait_for_lock = match value.parse() {
+               Ok(seconds) => seconds,
+               Err(_) => bail!("--wait-for-lock expects a number of seconds, got '{}'", value),
+            }
+         }
+         "--force-worktree" => options.force_worktree = enable,
+         "--no-hooks" => options.no_hooks = enable,
+         "--quiet-git" => options.quiet_git = enable,
+         "--progress" => {
+            options.progress = match take_value(flag, inline, &mut rest)?.as_str() {
+               "lines" => Progress::Lines,
+               "oneline" => Progress::Oneline,
+               other => bail!("--progress expects 'lines' or 'oneline', got '{}'", other),
+            }
+         }
+         "--format" => {
+            options.format = match take_value(flag, inline, &mut rest)?.as_str() {
+               "text" => Format::Text,
+               "json" => Format::Json,
+               other => bail!("--format expects 'text' or 'json', got '{}'", other),
+            }
+         }
+         "--summary-only" => options.summary_only = enable,
+         "--report-file" => options.report_file = Some(take_value(flag, inline, &mut rest)?),
+         "--notify-on-failure-url" => options.notify_on_failure_url = Some(take_value(flag, inline, &mut rest)?),
+         "--profile" => options.profile = Some(take_value(flag, inline, &mut rest)?),
+         "--changed-paths-file" => options.changed_paths_file = Some(take_value(flag, inline, &mut rest)?),
+         "--confirm-diff-threshold" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.confirm_diff_threshold = match value.parse() {
+               Ok(threshold) => Some(threshold),
+               Err(_) => bail!("--confirm-diff-threshold expects a number of files, got '{}'", value),
+            }
+         }
+         "--confirm-before-push" => options.confirm_before_push = enable,
+         "--pause" => options.pause = enable,
+         "--stat" => options.diff_stat = enable,
+         "--context" => {
+            let value = take_value(flag, inline, &mut rest)?;
+            options.diff_context = match value.parse() {
+               Ok(lines) => Some(lines),
+               _ => bail!("--context expects a number of lines, got '{}'", value),
+            }
+         }
+         "--ignore-whitespace" => options.ignore_whitespace = enable,
+         "--find-renames" => {
+            options.find_renames = Some(match inline {
+               Some(threshold)
+                  if threshold
+                     .trim_end_matches('%')
+                     .parse::<u8>()
+                     .is_ok_and(|percent| percent <= 100) =>
+               {
+                  threshold.to_string()
+               }
+               Some(other) => {
+                  bail!("--find-renames expects a similarity percentage such as 50%, got '{}'", other)
+               }
+               None => "50%".to_string(),
+            })
+         }
+         // Optional value, so only the `--color=<when>` spelling takes one.
+         "--color" => {
+            options.diff_color = Some(match inline {
+               Some(when @ ("always" | "never" | "auto")) => when.to_string(),
+               Some(other) => bail!("--color expects 'always', 'never' or 'auto', got '{}'", other),
+               None => "always".to_string(),
+            })
+         }
+         "--no-pager" => options.no_pager = enable,
+         "--prune" => options.prune_worktrees = enable,
+         "-y" | "--yes" => options.yes = enable,
+         "-h" | "--help" => options.help = true,
+         _ if flag.starts_with('-') && flag.len() > 1 => {
+            bail!("Unknown option '{}'. Run with --help for usage.", flag)
+         }
+         "diff" if words.is_empty() && options.subcommand == Subcommand::Sync => {
+            options.subcommand = Subcommand::Diff
+         }
+         "validate" if words.is_empty() && options.subcommand == Subcommand::Sync => {
+            options.subcommand = Subcommand::Validate
+         }
+         "worktrees" if words.is_empty() && options.subcommand == Subcommand::Sync => {
+            options.subcommand = Subcommand::Worktrees
+         }
+         _ => words.push(arg.clone()),
+      }
+   }
+   if options.subcommand == Subcommand::Diff && !words.is_empty() {
+      bail!("`labtohub diff` takes no message");
+   }
+   if options.subcommand == Subcommand::Validate && !words.is_empty() {
+      bail!("`labtohub validate` takes no message");
+   }
+   if options.subcommand == Subcommand::Worktrees && !words.is_empty() {
+      bail!("`labtohub worktrees` takes no message");
+   }
+   if options.message.is_none() && !words.is_empty() {
+      options.message = Some(words.join(" "));
+   }
+   Ok(options)
+}
+
+/// The checks of options that depend on each other, run once the layers are merged.
+fn check_combinations(mut options: Options) -> Result<Options> {
+   if options.no_auto_slug && options.branch.is_none() && options.subcommand == Subcommand::Sync {
+      bail!("--no-auto-slug is set, so the content branch must be named with --branch <NAME>");
+   }
+   if options.no_clean && options.clean_ignored {
+      bail!("--no-clean cannot be combined with --clean-ignored");
+   }
+   // The staging merge is a fast-forward, so only these merges have conflicts to resolve.
+   if options.merge_strategy_option.is_some()
+      && options.merge_paths.is_empty()
+      && options.extra_source_branches.is_empty()
+   {
+      bail!("--merge-strategy-option only resolves the conflicts of --merge-path or several \
+             --source-branch values");
+   }
+   if options.dest_subdir.is_some() && !options.merge_paths.is_empty() {
+      bail!("--merge-path cannot be combined with --dest-subdir");
+   }
+   if !options.includes.is_empty()
+      && (options.dest_subdir.is_some()
+         || options.since_last_sync
+         || options.since_tag.is_some()
+         || options.abort_if_behind)
+   {
+      bail!("--include cannot be combined with --dest-subdir, --since-last-sync, --since-tag or \
+             --abort-if-behind");
+   }
+   if options.strip_prefix.is_some()
+      && (options.dest_subdir.is_some()
+         || options.since_last_sync
+         || options.since_tag.is_some()
+         || !options.merge_paths.is_empty()
+         || !options.extra_source_branches.is_empty()
+         || options.abort_if_behind)
+   {
+      bail!("--strip-prefix cannot be combined with --dest-subdir, --since-last-sync, --since-tag, \
+             --merge-path, several --source-branch values or --abort-if-behind");
+   }
+   if options.base_ref.is_some() && (options.orphan_each_sync || options.push_compare) {
+      bail!("--base-ref picks what the destination branch is rebuilt on, so it cannot be combined with \
+             --orphan-each-sync or --push-compare");
+   }
+   if options.orphan_each_sync
+      && (options.since_last_sync
+         || options.since_tag.is_some()
+         || options.dest_subdir.is_some()
+         || !options.merge_paths.is_empty()
+         || options.diff_filter.is_some()
+         || !options.includes.is_empty())
+   {
+      bail!("--orphan-each-sync replaces github's tree outright, so it cannot be combined with \
+             --since-last-sync, --since-tag, --dest-subdir, --merge-path, --diff-filter or --include");
+   }
+   if !options.extra_source_branches.is_empty()
+      && (options.since_last_sync
+         || options.since_tag.is_some()
+         || options.dest_subdir.is_some()
+         || !options.merge_paths.is_empty()
+         || options.diff_filter.is_some()
+         || options.orphan_each_sync
+         || options.abort_if_behind
+         || options.verify_clean_after
+         || !options.includes.is_empty())
+   {
+      bail!("Several --source-branch values cannot be combined with --since-last-sync, --since-tag, \
+             --dest-subdir, --merge-path, --diff-filter, --orphan-each-sync, --abort-if-behind, \
+             --verify-clean-after or --include");
+   }
+   if options.replay
+      && (options.since_last_sync
+         || options.since_tag.is_some()
+         || options.orphan_each_sync
+         || options.strip_prefix.is_some()
+         || !options.includes.is_empty()
+         || !options.merge_paths.is_empty()
+         || options.diff_filter.is_some()
+         || options.vendor_submodules
+         || !options.extra_source_branches.is_empty())
+   {
+      bail!("--replay applies origin's commits one by one, so it cannot be combined with --since-last-sync, \
+             --since-tag, --orphan-each-sync, --strip-prefix, --include, --merge-path, --diff-filter, \
+             --vendor-submodules or several --source-branch values");
+   }
+   if options.no_fetch && (options.source_url.is_some() || options.dest_url.is_some()) {
+      bail!("--source-url and --dest-url add a remote for this run only, so there is nothing \
+             fetched to use with --no-fetch");
+   }
+   if options.since_last_sync && options.since_tag.is_some() {
+      bail!("--since-last-sync and --since-tag pick different baselines; use one of them");
+   }
+   if options.push_compare && (options.tag.is_some() || options.orphan_each_sync || options.verify_clean_after) {
+      bail!("--push-compare leaves the github branch alone, so it cannot be combined with \
+             --tag, --orphan-each-sync or --verify-clean-after");
+   }
+   if options.tag.is_none() && options.tag_message_template.is_some() {
+      bail!("--tag-message-template needs --tag <NAME>");
+   }
+   if options.github_release && options.tag.is_none() {
+      bail!("--github-release needs --tag <NAME>: a release is made from the pushed tag");
+   }
+   if !options.github_release && (options.prerelease || options.draft) {
+      bail!("--prerelease and --draft need --github-release");
+   }
+   if let Some(tag) = &options.source_tag {
+      if !options.extra_source_branches.is_empty() {
+         bail!("--source-ref copies one tag, so it cannot be combined with several --source-branch values");
+      }
+      if let Some(prefix) = &options.dest_branch_per_tag {
+         if options.detect_dest_branch {
+            bail!("--dest-branch-per-tag names the destination branch, so it cannot be combined with --dest-default-branch");
+         }
+         // Each release line gets a branch of its own, created on its first sync.
+         options.dest_branch = format!("{}{}", prefix, tag);
+         options.create_dest_branch = true;
+      }
+   } else if options.dest_branch_per_tag.is_some() {
+      bail!("--dest-branch-per-tag needs --source-ref <TAG>");
+   }
+   if !options.message_from_origin_log
+      && (options.log_summary_limit.is_some() || options.log_format != LogFormat::List)
+   {
+      bail!("--log-summary-limit and --log-format shape the generated message, so they need --message-from-origin-log");
+   }
+   if options.no_worktree && options.tmp_cleanup_on_start_only {
+      bail!("--no-worktree builds in the checkout and always restores it, so there is no worktree for \
+             --tmp-cleanup-on-start-only to keep");
+   }
+   if options.no_worktree && options.git_dir.is_some() {
+      bail!("--no-worktree builds in the checkout of the current directory's repository, so it cannot be \
+             combined with --git-dir");
+   }
+   if options.vendor_submodules
+      && (options.since_last_sync
+         || options.since_tag.is_some()
+         || options.strip_prefix.is_some()
+         || !options.includes.is_empty()
+         || !options.extra_source_branches.is_empty()
+         || options.verify_clean_after)
+   {
+      bail!("--vendor-submodules rewrites the whole tree, so it cannot be combined with --since-last-sync, \
+             --since-tag, --strip-prefix, --include, several --source-branch values or --verify-clean-after");
+   }
+   if options.build_only {
+      if options.dry_run || options.push_compare || options.tag.is_some() {
+         bail!("--build-only stops before anything is merged or pushed, so it cannot be combined with \
+                --dry-run, --push-compare or --tag");
+      }
+      // The commit is there to be looked at, so the worktree outlives the run.
+      options.tmp_cleanup_on_start_only = true;
+   }
+   if options.shallow_since.is_some() && options.depth.is_some() {
+      bail!("--shallow-since and --depth both limit the history fetched; use one of them");
+   }
+   if options.strict_message && options.max_subject_length.is_none() {
+      bail!("--strict-message needs --max-subject-length <N>");
+   }
+   if options.allowed_signers.is_some() && !options.verify_signatures {
+      bail!("--allowed-signers needs --verify-signatures");
+   }
+   let diff_flags = options.diff_stat
+      || options.diff_context.is_some()
+      || options.diff_color.is_some()
+      || options.no_pager;
+   if diff_flags && options.subcommand != Subcommand::Diff {
+      bail!("--stat, --context, --color and --no-pager only apply to `labtohub diff`");
+   }
+   if options.prune_worktrees && options.subcommand != Subcommand::Worktrees {
+      bail!("--prune only applies to `labtohub worktrees`");
+   }
+   if options.summary_only && options.format == Format::Json {
+      bail!("--summary-only cannot be combined with --format json");
+   }
+   Ok(options)
+}
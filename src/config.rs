@@ -0,0 +1,426 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+
+use crate::cli::{parse_args, parse_tokens, Options};
+
+pub const CONFIG_FILE: &str = ".labtohub.toml";
+
+/// Environment variables understood by labtohub and the option each one sets.
+pub const ENV_OPTIONS: &[(&str, &str)] = &[
+   ("LABTOHUB_MESSAGE", "--message"),
+   ("LABTOHUB_SOURCE_REMOTE", "--source-remote"),
+   ("LABTOHUB_DEST_REMOTE", "--dest-remote"),
+   ("LABTOHUB_SOURCE_BRANCH", "--source-branch"),
+   ("LABTOHUB_DEST_BRANCH", "--dest-branch"),
+   ("LABTOHUB_YES", "--yes"),
+];
+
+/// Options that are switches rather than taking a value, so env vars set them by truthiness.
+const ENV_SWITCHES: &[&str] = &["--yes"];
+
+/// Options that can be given more than once. A layer that sets one replaces the values of
+/// the layers below instead of adding to them.
+const REPEATABLE: &[&str] = &[
+   "--source-branch",
+   "--trailer",
+   "--include",
+   "--merge-path",
+   "--push-option",
+   "--fetch-refspec",
+];
+
+fn is_truthy(value: &str) -> bool {
+   matches!(
+      value.trim().to_ascii_lowercase().as_str(),
+      "1" | "true" | "yes" | "on"
+   )
+}
+
+/// Arguments equivalent to the `LABTOHUB_*` environment variables that are set.
+pub fn env_args() -> Vec<String> {
+   args_from_env(|var| env::var(var).ok())
+}
+
+/// `env_args` with the variables looked up through `var`.
+fn args_from_env(var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+   let mut args = Vec::new();
+   for (name, flag) in ENV_OPTIONS {
+      let Some(value) = var(name) else {
+         continue;
+      };
+      if ENV_SWITCHES.contains(flag) {
+         // A false value turns off a switch the config file set.
+         match is_truthy(&value) {
+            true => args.push(flag.to_string()),
+            false if !value.trim().is_empty() => args.push(format!("--no-{}", &flag[2..])),
+            false => {}
+         }
+      } else if !value.is_empty() {
+         args.push(flag.to_string());
+         args.push(value);
+      }
+   }
+   args
+}
+
+/// A value in the small TOML subset labtohub reads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+   String(String),
+   Bool(bool),
+   Integer(i64),
+   Array(Vec<Value>),
+}
+
+fn parse_string(text: &str, quote: char) -> Result<(String, &str)> {
+   let mut out = String::new();
+   let mut chars = text.char_indices();
+   while let Some((index, c)) = chars.next() {
+      match c {
+         c if c == quote => return Ok((out, &text[index + 1..])),
+         '\\' if quote == '"' => match chars.next() {
+            Some((_, 'n')) => out.push('\n'),
+            Some((_, 't')) => out.push('\t'),
+            Some((_, '"')) => out.push('"'),
+            Some((_, '\\')) => out.push('\\'),
+            Some((_, other)) => bail!("unsupported escape '\\{}'", other),
+            None => break,
+         },
+         c => out.push(c),
+      }
+   }
+   bail!("unterminated string")
+}
+
+/// Parses one value and returns whatever text follows it.
+fn parse_value(text: &str) -> Result<(Value, &str)> {
+   let text = text.trim_start();
+   let mut chars = text.chars();
+   match chars.next() {
+      Some(quote @ ('"' | '\'')) => {
+         let (value, rest) = parse_string(chars.as_str(), quote)?;
+         Ok((Value::String(value), rest))
+      }
+      Some('[') => {
+         let mut items = Vec::new();
+         let mut rest = chars.as_str().trim_start();
+         loop {
+            if let Some(after) = rest.strip_prefix(']') {
+               return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+               rest = after.trim_start();
+            } else if !rest.starts_with(']') {
+               bail!("expected ',' or ']' in array");
+            }
+         }
+      }
+      _ => {
+         let end = text
+            .find(|c: char| c == ',' || c == ']' || c == '#' || c.is_whitespace())
+            .unwrap_or(text.len());
+         let (word, rest) = text.split_at(end);
+         let value = match word {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => match word.replace('_', "").parse::<i64>() {
+               Ok(number) => Value::Integer(number),
+               Err(_) => bail!("unsupported value '{}'", word),
+            },
+         };
+         Ok((value, rest))
+      }
+   }
+}
+
+/// Reads `key = value` pairs, keyed by their `[section]` ("" for the top level).
+pub fn parse_config(text: &str) -> Result<Vec<(String, String, Value)>> {
+   let mut entries = Vec::new();
+   let mut section = String::new();
+   for (number, line) in text.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+         continue;
+      }
+      let context = || format!("line {}", number + 1);
+      if let Some(name) = line.strip_prefix('[') {
+         let Some(name) = name.trim_end().strip_suffix(']') else {
+            bail!("{}: malformed section header", context());
+         };
+         section = name.trim().to_string();
+         continue;
+      }
+      let Some((key, value)) = line.split_once('=') else {
+         bail!("{}: expected `key = value`", context());
+      };
+      let (value, rest) = parse_value(value).with_context(context)?;
+      let rest = rest.trim();
+      if !rest.is_empty() && !rest.starts_with('#') {
+         bail!("{}: unexpected text after value", context());
+      }
+      entries.push((section.clone(), key.trim().to_string(), value));
+   }
+   Ok(entries)
+}
+
+fn push_value_args(args: &mut Vec<String>, flag: &str, value: &Value) -> Result<()> {
+   match value {
+      Value::Bool(true) => args.push(flag.to_string()),
+      Value::Bool(false) => {}
+      Value::String(text) => {
+         args.push(flag.to_string());
+         args.push(text.clone());
+      }
+      Value::Integer(number) => {
+         args.push(flag.to_string());
+         args.push(number.to_string());
+      }
+      // Arrays stand for repeatable options such as `merge_path = ["a", "b"]`.
+      Value::Array(items) => {
+         for item in items {
+            if matches!(item, Value::Array(_)) {
+               bail!("nested arrays are not supported for '{}'", flag);
+            }
+            push_value_args(args, flag, item)?;
+         }
+      }
+   }
+   Ok(())
+}
+
+/// Section of `.labtohub.toml` that `--profile <name>` selects.
+fn profile_section(name: &str) -> String {
+   format!("profile.{}", name)
+}
+
+/// Arguments equivalent to the top-level keys of `.labtohub.toml`, where `dest_branch = "x"`
+/// means `--dest-branch x` and `yes = true` means `--yes`. With a `profile`, the keys of its
+/// `[profile.<name>]` section replace the top-level keys they share and add the rest.
+pub fn config_args(profile: Option<&str>) -> Result<Vec<String>> {
+   let text = match fs::read_to_string(CONFIG_FILE) {
+      Ok(text) => text,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => match profile {
+         Some(name) => bail!(
+            "--profile {} needs a {} with a [{}] section",
+            name,
+            CONFIG_FILE,
+            profile_section(name)
+         ),
+         None => return Ok(Vec::new()),
+      },
+      Err(err) => return Err(err).with_context(|| format!("Could not read {}", CONFIG_FILE)),
+   };
+   args_from_config(&text, profile)
+}
+
+/// `config_args` for the file's `text`.
+fn args_from_config(text: &str, profile: Option<&str>) -> Result<Vec<String>> {
+   let entries = parse_config(text).with_context(|| format!("Invalid {}", CONFIG_FILE))?;
+   let mut selected = entries
+      .iter()
+      .filter(|(section, _, _)| section.is_empty())
+      .collect::<Vec<_>>();
+   if let Some(name) = profile {
+      let section = profile_section(name);
+      let overrides = entries
+         .iter()
+         .filter(|(entry_section, _, _)| *entry_section == section)
+         .collect::<Vec<_>>();
+      if overrides.is_empty() {
+         let mut known = entries
+            .iter()
+            .filter_map(|(entry_section, _, _)| entry_section.strip_prefix("profile."))
+            .collect::<Vec<_>>();
+         known.sort();
+         known.dedup();
+         if known.is_empty() {
+            bail!("{} has no [{}] section for --profile, and defines no profiles", CONFIG_FILE, section);
+         }
+         bail!("{} has no [{}] section for --profile; it defines {}", CONFIG_FILE, section, known.join(", "));
+      }
+      selected.retain(|(_, key, _)| !overrides.iter().any(|(_, override_key, _)| override_key == key));
+      selected.extend(overrides);
+   }
+   let mut args = Vec::new();
+   for (_, key, value) in selected {
+      let flag = format!("--{}", key.replace('_', "-"));
+      push_value_args(&mut args, &flag, value).with_context(|| format!("Invalid {}", CONFIG_FILE))?;
+   }
+   Ok(args)
+}
+
+/// Drops `<flag> <VALUE>` pairs, spelled the way only `env_args` and `config_args` produce them.
+fn without_option(args: Vec<String>, flag: &str) -> Vec<String> {
+   let mut kept = Vec::new();
+   let mut args = args.into_iter();
+   while let Some(arg) = args.next() {
+      if arg == flag {
+         args.next();
+      } else {
+         kept.push(arg);
+      }
+   }
+   kept
+}
+
+fn mentions(args: &[String], flag: &str) -> bool {
+   args
+      .iter()
+      .any(|arg| arg == flag || arg.starts_with(&format!("{}=", flag)))
+}
+
+/// Options from the command line, then `LABTOHUB_*` variables, then `.labtohub.toml`,
+/// then the built-in defaults, each filling in only what the ones before left unset.
+pub fn load_options(argv: &[String]) -> Result<Options> {
+   let explicit = parse_tokens(argv)?;
+   if explicit.help {
+      return Ok(explicit);
+   }
+   parse_args(&layered_args(argv)?)
+}
+
+/// The config file's options, then the environment's, then `argv`, with the repeatable or
+/// whole-message options of the lower layers dropped where a higher layer sets them. Other
+/// options, switches included, are simply taken from the last layer that gives them.
+pub fn layered_args(argv: &[String]) -> Result<Vec<String>> {
+   let explicit = parse_tokens(argv)?;
+   let config = config_args(explicit.profile.as_deref())?;
+   parse_tokens(&config).with_context(|| format!("Invalid option in {}", CONFIG_FILE))?;
+   Ok(layer(config, env_args(), argv, &explicit))
+}
+
+/// `layered_args` for the given config file and environment arguments; `explicit` is what
+/// `argv` alone parses to.
+fn layer(config: Vec<String>, env: Vec<String>, argv: &[String], explicit: &Options) -> Vec<String> {
+   let mut layered = config;
+   for flag in REPEATABLE {
+      if mentions(&env, flag) {
+         layered = without_option(layered, flag);
+      }
+   }
+   layered.extend(env);
+   for flag in REPEATABLE {
+      if mentions(argv, flag) {
+         layered = without_option(layered, flag);
+      }
+   }
+   // A message given any way on the command line replaces the configured one outright.
+   if explicit.message.is_some() || explicit.message_file.is_some() {
+      layered = without_option(layered, "--message");
+   }
+   layered.extend(argv.iter().cloned());
+   layered
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   const PROFILES: &str = r#"
+dest_branch = "main"
+dest_remote = "github"
+merge_path = ["README.md"]
+
+[profile.enterprise]
+dest_remote = "ghe"
+merge_path = ["docs/**"]
+yes = true
+
+[profile.public]
+no_hooks = true
+"#;
+
+   #[test]
+   fn profile_overrides_top_level_keys() {
+      let args = args_from_config(PROFILES, Some("enterprise")).unwrap();
+      assert_eq!(
+         args,
+         ["--dest-branch", "main", "--dest-remote", "ghe", "--merge-path", "docs/**", "--yes"]
+      );
+      let options = parse_args(&args).unwrap();
+      assert_eq!(options.dest_remote, "ghe");
+      assert_eq!(options.merge_paths, ["docs/**"]);
+   }
+
+   #[test]
+   fn no_profile_ignores_profile_sections() {
+      assert_eq!(
+         args_from_config(PROFILES, None).unwrap(),
+         ["--dest-branch", "main", "--dest-remote", "github", "--merge-path", "README.md"]
+      );
+   }
+
+   #[test]
+   fn unknown_profile_lists_the_defined_ones() {
+      let err = args_from_config(PROFILES, Some("staging")).unwrap_err();
+      assert!(err.to_string().contains("it defines enterprise, public"), "{}", err);
+      assert!(args_from_config("dest_branch = \"main\"\n", Some("staging")).is_err());
+   }
+
+   fn strings(args: &[&str]) -> Vec<String> {
+      args.iter().map(|arg| arg.to_string()).collect()
+   }
+
+   fn resolve(config: &[&str], env: Vec<String>, argv: &[&str]) -> Options {
+      let argv = strings(argv);
+      let explicit = parse_tokens(&argv).unwrap();
+      parse_tokens(&strings(config)).unwrap();
+      parse_args(&layer(strings(config), env, &argv, &explicit)).unwrap()
+   }
+
+   #[test]
+   fn env_vars_alone_resolve_to_options() {
+      let env = args_from_env(|name| match name {
+         "LABTOHUB_MESSAGE" => Some("Sync from CI".to_string()),
+         "LABTOHUB_DEST_REMOTE" => Some("mirror".to_string()),
+         "LABTOHUB_SOURCE_BRANCH" => Some("release".to_string()),
+         "LABTOHUB_YES" => Some("on".to_string()),
+         _ => None,
+      });
+      let options = resolve(&[], env, &[]);
+      assert_eq!(options.message.as_deref(), Some("Sync from CI"));
+      assert_eq!(options.dest_remote, "mirror");
+      assert_eq!(options.source_remote, "origin");
+      assert_eq!(options.source_branch, "release");
+      assert!(options.yes);
+   }
+
+   #[test]
+   fn higher_layers_replace_repeatable_options() {
+      let config = ["--include", "src", "--include", "docs", "--trailer", "Ticket=OPS-1"];
+      let options = resolve(&config, Vec::new(), &["--include", "README.md"]);
+      assert_eq!(options.includes, ["README.md"]);
+      assert_eq!(options.trailers, ["Ticket: OPS-1"]);
+
+      let env = strings(&["--source-branch", "release"]);
+      let options = resolve(&["--source-branch", "main", "--source-branch", "next"], env, &[]);
+      assert_eq!(options.source_branch, "release");
+      assert!(options.extra_source_branches.is_empty());
+   }
+
+   #[test]
+   fn switches_can_be_turned_off_by_a_higher_layer() {
+      let options = resolve(&["--yes", "--no-hooks"], Vec::new(), &["--no-yes", "--hooks"]);
+      assert!(!options.yes);
+      assert!(!options.no_hooks);
+
+      let env = args_from_env(|name| (name == "LABTOHUB_YES").then(|| "0".to_string()));
+      assert_eq!(env, ["--no-yes"]);
+      assert!(!resolve(&["--yes"], env, &[]).yes);
+   }
+
+   #[test]
+   fn dependencies_may_be_split_between_config_and_argv() {
+      let options = resolve(&["--max-subject-length", "50"], Vec::new(), &["validate", "--strict-message"]);
+      assert!(options.strict_message);
+      assert_eq!(options.max_subject_length, Some(50));
+      assert!(resolve(&["--tag", "v1"], Vec::new(), &["--github-release"]).github_release);
+      assert_eq!(resolve(&["--no-auto-slug"], Vec::new(), &["--branch", "x"]).branch.as_deref(), Some("x"));
+      let options = resolve(&["--merge-path", "CHANGELOG.md"], Vec::new(), &["--merge-strategy-option", "ours"]);
+      assert_eq!(options.merge_paths, ["CHANGELOG.md"]);
+   }
+}
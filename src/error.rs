@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// Failures callers may want to tell apart. Everything else stays a plain `anyhow` message;
+/// `kind_of` reports those as "other".
+#[derive(Debug)]
+pub enum LabtohubError {
+   /// `git` could not be started at all.
+   GitNotFound,
+   /// A git command exited unsuccessfully.
+   CommandFailed { command: String },
+   /// The clone has no remote by this name.
+   RemoteNotFound {
+      remote: String,
+      role: &'static str,
+      flag: &'static str,
+   },
+   /// A branch or ref labtohub needs does not exist.
+   RefMissing { reference: String },
+   /// The remote refused our credentials; `hint` says what to check.
+   AuthFailed { remote: String, hint: &'static str },
+   /// The destination branch moved between our fetch and push, so the push was not a fast-forward.
+   PushRejected { remote: String },
+   /// `--merge-path` files, or files of several `--source-branch`es, that could not be merged cleanly.
+   MergeConflict { paths: Vec<String> },
+}
+
+impl LabtohubError {
+   /// Stable, machine-readable name for `--format json` and `--summary-only`.
+   pub fn kind(&self) -> &'static str {
+      match self {
+         LabtohubError::GitNotFound => "git-not-found",
+         LabtohubError::CommandFailed { .. } => "command-failed",
+         LabtohubError::RemoteNotFound { .. } => "remote-not-found",
+         LabtohubError::RefMissing { .. } => "ref-missing",
+         LabtohubError::AuthFailed { .. } => "auth-failed",
+         LabtohubError::PushRejected { .. } => "push-rejected",
+         LabtohubError::MergeConflict { .. } => "merge-conflict",
+      }
+   }
+}
+
+impl fmt::Display for LabtohubError {
+   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+         LabtohubError::GitNotFound => {
+            write!(f, "Could not run git; make sure it is installed and on PATH")
+         }
+         LabtohubError::CommandFailed { command } => write!(f, "Command failed: {}", command),
+         LabtohubError::RemoteNotFound { remote, role, flag } => write!(
+            f,
+            "There is no '{}' remote to {}; add it with `git remote add {} <url>` or pass {} <REMOTE>",
+            remote, role, remote, flag
+         ),
+         LabtohubError::RefMissing { reference } => {
+            write!(f, "'{}' does not exist; check the remote and branch names", reference)
+         }
+         LabtohubError::AuthFailed { remote, hint } => {
+            write!(f, "Authentication to remote '{}' failed. {}", remote, hint)
+         }
+         LabtohubError::PushRejected { remote } => write!(
+            f,
+            "Push to '{}' was rejected because the branch moved since it was fetched. \
+             Re-run to sync onto the new tip, or pass --retry-push-on-reject <N>.",
+            remote
+         ),
+         LabtohubError::MergeConflict { paths } => write!(
+            f,
+            "Aborted: merge conflicts in {}; resolve them upstream or pass \
+             --merge-strategy-option=ours|theirs (--merge-path-resolve for --merge-path files only)",
+            paths.join(", ")
+         ),
+      }
+   }
+}
+
+impl std::error::Error for LabtohubError {}
+
+/// The `LabtohubError` kind behind `err`, or "other" for unstructured failures.
+pub fn kind_of(err: &anyhow::Error) -> &'static str {
+   err.downcast_ref::<LabtohubError>()
+      .map_or("other", LabtohubError::kind)
+}
@@ -0,0 +1,147 @@
+use anyhow::{bail, Result};
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::cli::Options;
+use crate::compare::web_url;
+use crate::output::JsonObject;
+use crate::{run_output, short_sha};
+
+/// The API URL of the destination remote's repository: `api.github.com` for github.com, and
+/// GitHub Enterprise's `/api/v3` on any other host.
+fn api_url(remote_url: &str) -> Option<String> {
+   let web = web_url(remote_url)?;
+   let (scheme, rest) = web.split_once("://")?;
+   let (host, path) = rest.split_once('/')?;
+   Some(match host {
+      "github.com" | "www.github.com" => format!("https://api.github.com/repos/{}", path),
+      _ => format!("{}://{}/api/v3/repos/{}", scheme, host, path),
+   })
+}
+
+/// `GH_TOKEN`, else `GITHUB_TOKEN`, as the `gh` CLI and GitHub Actions set them.
+fn token() -> Option<String> {
+   ["GH_TOKEN", "GITHUB_TOKEN"]
+      .iter()
+      .find_map(|name| env::var(name).ok().filter(|token| !token.is_empty()))
+}
+
+/// Quotes `value` for a curl config file, where `\` and `"` are escaped inside quotes.
+pub fn curl_quote(value: &str) -> String {
+   format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The first `"key":"value"` string in a JSON response, which is enough to find a release's
+/// `html_url` or an error's `message` without a JSON parser.
+pub fn json_field(text: &str, key: &str) -> Option<String> {
+   let start = text.find(&format!("\"{}\"", key))? + key.len() + 2;
+   let rest = text[start..].trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+   Some(rest[..rest.find('"')?].to_string())
+}
+
+/// Sends `body` to `path` under the destination repository's API URL with curl, and returns
+/// the response. The token and body go through curl's config on stdin, so neither shows up
+/// in the process list. Fails without a token, on a non-GitHub remote, or on an HTTP error.
+pub fn request(options: &Options, method: &str, path: &str, body: &str) -> Result<String> {
+   let Some(token) = token() else {
+      bail!("set GH_TOKEN or GITHUB_TOKEN to a token with access to the repository");
+   };
+   let remote_url = run_output("git", &["remote", "get-url", &options.dest_remote])?;
+   let Some(base) = api_url(&remote_url) else {
+      bail!("'{}' is not a GitHub URL", remote_url);
+   };
+   let config = [
+      format!("url = {}", curl_quote(&format!("{}{}", base, path))),
+      format!("request = {}", curl_quote(method)),
+      format!("header = {}", curl_quote(&format!("Authorization: Bearer {}", token))),
+      format!("header = {}", curl_quote("Accept: application/vnd.github+json")),
+      format!("header = {}", curl_quote("Content-Type: application/json")),
+      format!("data-binary = {}", curl_quote(body)),
+   ]
+   .join("\n");
+   let mut child = Command::new("curl")
+      .args(["--silent", "--show-error", "--config", "-", "--write-out", "\n%{http_code}"])
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|_| anyhow::anyhow!("curl is not installed or not on PATH"))?;
+   child
+      .stdin
+      .take()
+      .expect("curl stdin is piped")
+      .write_all(config.as_bytes())?;
+   let output = child.wait_with_output()?;
+   if !output.status.success() {
+      bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+   }
+   let text = String::from_utf8_lossy(&output.stdout).to_string();
+   let (response, status) = text.rsplit_once('\n').unwrap_or(("", text.as_str()));
+   if !status.trim().starts_with('2') {
+      let reason = json_field(response, "message").unwrap_or_else(|| response.trim().to_string());
+      bail!("GitHub answered HTTP {}: {}", status.trim(), reason);
+   }
+   Ok(response.to_string())
+}
+
+/// The JSON that makes `branch` the repository's default branch.
+fn default_branch_body(branch: &str) -> String {
+   JsonObject::new().string("default_branch", branch).render()
+}
+
+/// `--set-dest-head`: after the push that created the destination branch, makes it the
+/// repository's default, so a new mirror does not keep whatever GitHub picked. Without a
+/// token, or when the API call fails, it says how to do it by hand instead of failing.
+pub fn set_default_branch(options: &Options) {
+   let Some(branch) = options.push_ref().strip_prefix("refs/heads/").map(str::to_string) else {
+      say!(
+         "Note: {} is not a branch, so it is not made the default (--set-dest-head).",
+         options.push_ref()
+      );
+      return;
+   };
+   say!("Making '{}' the default branch of {} (--set-dest-head)...", branch, options.dest_remote);
+   match request(options, "PATCH", "", &default_branch_body(&branch)) {
+      Ok(_) => {
+         // Keep the clone's idea of the remote HEAD in step, as a fresh clone would have it.
+         let _ = run_output("git", &["remote", "set-head", &options.dest_remote, &branch]);
+         say!("{}'s default branch is now '{}'.", options.dest_remote, branch);
+      }
+      Err(err) => say!(
+         "Note: could not set the default branch ({:#}); set it to '{}' in the repository \
+          settings, or with `gh repo edit --default-branch {}`.",
+         err,
+         branch,
+         branch
+      ),
+   }
+}
+
+/// The context the commit status is posted under.
+pub const STATUS_CONTEXT: &str = "labtohub/sync";
+
+/// The JSON of a commit status in the `labtohub/sync` context.
+fn status_body(state: &str, description: &str) -> String {
+   JsonObject::new()
+      .string("state", state)
+      .string("context", STATUS_CONTEXT)
+      .string("description", description)
+      .render()
+}
+
+/// `--set-status`: posts a commit status on the pushed `sha`, so checks downstream can wait
+/// for the mirror. The push is done by then, so a failure only warns.
+pub fn set_status(options: &Options, state: &str, sha: &str, source_sha: &str) {
+   say!(
+      "Setting the {} status of {} to '{}' (--set-status)...",
+      STATUS_CONTEXT,
+      short_sha(sha),
+      state
+   );
+   let description = format!("Mirrored from {}@{}", options.source_ref(), short_sha(source_sha));
+   let path = format!("/statuses/{}", sha);
+   if let Err(err) = request(options, "POST", &path, &status_body(state, &description)) {
+      say!("Warning: could not set the commit status: {:#}", err);
+   }
+}
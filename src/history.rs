@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::run_output;
+
+const HISTORY_FILE: &str = "labtohub-history";
+
+/// One successful sync, stored as a tab-separated line in the history log.
+#[derive(Debug, Clone)]
+pub struct Entry {
+   pub timestamp: u64,
+   pub dest_branch: String,
+   pub source_sha: String,
+   pub dest_sha: String,
+   pub message: String,
+}
+
+impl Entry {
+   pub fn new(dest_branch: &str, source_sha: &str, dest_sha: &str, message: &str) -> Self {
+      Entry {
+         timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0),
+         dest_branch: dest_branch.to_string(),
+         source_sha: source_sha.to_string(),
+         dest_sha: dest_sha.to_string(),
+         message: message.to_string(),
+      }
+   }
+
+   fn to_line(&self) -> String {
+      format!(
+         "{}\t{}\t{}\t{}\t{}",
+         self.timestamp,
+         escape(&self.dest_branch),
+         self.source_sha,
+         self.dest_sha,
+         escape(&self.message)
+      )
+   }
+
+   fn from_line(line: &str) -> Option<Self> {
+      let mut fields = line.splitn(5, '\t');
+      Some(Entry {
+         timestamp: fields.next()?.parse().ok()?,
+         dest_branch: unescape(fields.next()?),
+         source_sha: fields.next()?.to_string(),
+         dest_sha: fields.next()?.to_string(),
+         message: unescape(fields.next()?),
+      })
+   }
+}
+
+fn escape(value: &str) -> String {
+   value
+      .replace('\\', "\\\\")
+      .replace('\t', "\\t")
+      .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+   let mut out = String::with_capacity(value.len());
+   let mut chars = value.chars();
+   while let Some(c) = chars.next() {
+      if c != '\\' {
+         out.push(c);
+         continue;
+      }
+      match chars.next() {
+         Some('t') => out.push('\t'),
+         Some('n') => out.push('\n'),
+         Some(other) => out.push(other),
+         None => out.push('\\'),
+      }
+   }
+   out
+}
+
+/// The log lives in the shared git dir so every worktree of the clone sees the same history.
+fn history_path() -> Result<PathBuf> {
+   let git_dir = run_output("git", &["rev-parse", "--git-common-dir"])?;
+   Ok(PathBuf::from(git_dir).join(HISTORY_FILE))
+}
+
+pub fn append(entry: &Entry) -> Result<()> {
+   let mut file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(history_path()?)?;
+   writeln!(file, "{}", entry.to_line())?;
+   Ok(())
+}
+
+pub fn entries() -> Result<Vec<Entry>> {
+   let contents = match fs::read_to_string(history_path()?) {
+      Ok(contents) => contents,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(err) => return Err(err.into()),
+   };
+   Ok(contents.lines().filter_map(Entry::from_line).collect())
+}
+
+/// Most recent recorded sync onto `dest_branch`, if any.
+pub fn last_sync(dest_branch: &str) -> Result<Option<Entry>> {
+   Ok(entries()?
+      .into_iter()
+      .rev()
+      .find(|entry| entry.dest_branch == dest_branch))
+}
@@ -0,0 +1,125 @@
+use anyhow::{bail, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::Options;
+use crate::{run_with_env, worktree_dir};
+
+pub const HOOKS_DIR: &str = ".labtohub/hooks";
+
+/// What a hook can see about the current sync. Empty values are not exported.
+pub struct HookContext<'a> {
+   pub branch: &'a str,
+   pub message: &'a str,
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+   use std::os::unix::fs::PermissionsExt;
+   path
+      .metadata()
+      .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+      .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+   path.is_file()
+}
+
+/// Whether a sync would run `.labtohub/hooks/<name>`.
+pub fn exists(options: &Options, name: &str) -> bool {
+   !options.no_hooks && Path::new(HOOKS_DIR).join(name).is_file()
+}
+
+/// Runs `.labtohub/hooks/<name>` if present. A failing `pre-*` hook aborts the sync,
+/// a failing `post-*` hook only warns, mirroring git's own hooks.
+pub fn run_hook(options: &Options, name: &str, context: &HookContext) -> Result<()> {
+   if options.no_hooks {
+      return Ok(());
+   }
+   let path = Path::new(HOOKS_DIR).join(name);
+   if !path.is_file() {
+      return Ok(());
+   }
+   if !is_executable(&path) {
+      say!(
+         "Warning: hook '{}' is not executable; skipping it.",
+         path.display()
+      );
+      return Ok(());
+   }
+
+   let mut env = vec![
+      ("LABTOHUB_HOOK", name.to_string()),
+      ("LABTOHUB_SOURCE_REF", options.source_ref()),
+      ("LABTOHUB_DEST_REF", options.dest_ref()),
+      ("LABTOHUB_STAGING_BRANCH", options.staging_branch()),
+      ("LABTOHUB_WORKTREE", worktree_dir().to_string()),
+   ];
+   if !context.branch.is_empty() {
+      env.push(("LABTOHUB_BRANCH", context.branch.to_string()));
+   }
+   if !context.message.is_empty() {
+      env.push(("LABTOHUB_MESSAGE", context.message.to_string()));
+   }
+
+   say!("Running {} hook...", name);
+   // The hook's own output should not land on the status line.
+   crate::output::end_status_line();
+   let program = path.to_string_lossy();
+   match run_with_env(&program, &[], &env) {
+      Ok(()) => Ok(()),
+      Err(_) if name.starts_with("post-") => {
+         say!("Warning: {} hook failed; continuing.", name);
+         Ok(())
+      }
+      Err(_) => bail!("{} hook failed; aborting the sync", name),
+   }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+   let mut shell = Command::new("sh");
+   shell.arg("-c").arg(command);
+   shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+   let mut shell = Command::new("cmd");
+   shell.arg("/C").arg(command);
+   shell
+}
+
+/// `--verify-command`: runs the command through the shell inside the worktree once the
+/// content commit exists, so it checks exactly what would be published. A non-zero exit
+/// aborts the sync before anything is merged or pushed.
+pub fn run_verify_command(
+   options: &Options,
+   context: &HookContext,
+   commit: &str,
+   changed_paths: &Path,
+) -> Result<()> {
+   let Some(command) = &options.verify_command else {
+      return Ok(());
+   };
+   say!("Running --verify-command in {}: {}", worktree_dir(), command);
+   crate::output::end_status_line();
+   let status = shell(command)
+      .current_dir(worktree_dir())
+      .env("LABTOHUB_COMMIT", commit)
+      .env("LABTOHUB_CHANGED_PATHS_FILE", changed_paths)
+      .env("LABTOHUB_BRANCH", context.branch)
+      .env("LABTOHUB_SOURCE_REF", options.source_ref())
+      .env("LABTOHUB_DEST_REF", options.dest_ref())
+      .status();
+   match status {
+      Ok(status) if status.success() => Ok(()),
+      Ok(status) => bail!(
+         "--verify-command failed ({}); aborting the sync before anything is pushed",
+         status
+      ),
+      Err(err) => bail!("Could not run --verify-command: {}", err),
+   }
+}
@@ -0,0 +1,142 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::run_output;
+
+const LOCK_FILE: &str = "labtohub.lock";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Held for as long as this run uses the worktree and staging branch; removing it on drop lets
+/// the next run in. A run killed before it can drop the lock leaves it behind, and the next
+/// run takes it over once the process that wrote it is gone.
+pub struct Lock {
+   path: PathBuf,
+}
+
+impl Drop for Lock {
+   fn drop(&mut self) {
+      let _ = fs::remove_file(&self.path);
+   }
+}
+
+fn lock_path() -> Result<PathBuf> {
+   let common_dir = run_output("git", &["rev-parse", "--git-common-dir"])?;
+   Ok(PathBuf::from(common_dir).join(LOCK_FILE))
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+   Command::new("kill")
+      .args(["-0", &pid.to_string()])
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .status()
+      .map_or(true, |status| status.success())
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+   true
+}
+
+/// The pid in an existing lock, or `None` when the lock is unreadable (e.g. still being written).
+fn holder(path: &PathBuf) -> Option<u32> {
+   fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn try_acquire(path: &PathBuf) -> Result<bool> {
+   match OpenOptions::new().write(true).create_new(true).open(path) {
+      Ok(mut file) => {
+         writeln!(file, "{}", std::process::id())?;
+         Ok(true)
+      }
+      Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+      Err(err) => {
+         Err(err).with_context(|| format!("Could not create lock file '{}'", path.display()))
+      }
+   }
+}
+
+/// Takes the repository's labtohub lock, waiting up to `wait` for another run to release it.
+pub fn acquire(wait: Duration) -> Result<Lock> {
+   acquire_at(lock_path()?, wait)
+}
+
+fn acquire_at(path: PathBuf, wait: Duration) -> Result<Lock> {
+   let deadline = Instant::now() + wait;
+   let mut announced = false;
+   loop {
+      if try_acquire(&path)? {
+         return Ok(Lock { path });
+      }
+      let pid = holder(&path);
+      if let Some(pid) = pid.filter(|&pid| !is_running(pid)) {
+         say!("Removing stale lock '{}' left by process {}.", path.display(), pid);
+         let _ = fs::remove_file(&path);
+         continue;
+      }
+      let owner = pid.map_or("another labtohub run".to_string(), |pid| {
+         format!("another labtohub run (pid {})", pid)
+      });
+      if Instant::now() >= deadline {
+         if wait.is_zero() {
+            bail!(
+               "{} is using this repository ('{}' exists); wait for it, or pass --wait-for-lock <SECS>",
+               owner,
+               path.display()
+            );
+         }
+         bail!(
+            "Gave up after {}s waiting for {} to finish (--wait-for-lock)",
+            wait.as_secs(),
+            owner
+         );
+      }
+      if !announced {
+         say!("Waiting for {} to finish (up to {}s)...", owner, wait.as_secs());
+         announced = true;
+      }
+      thread::sleep(POLL_INTERVAL);
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn scratch_lock(name: &str) -> PathBuf {
+      let path = std::env::temp_dir().join(format!("labtohub-lock-{}-{}", name, std::process::id()));
+      let _ = fs::remove_file(&path);
+      path
+   }
+
+   #[test]
+   fn held_lock_times_out() {
+      let path = scratch_lock("held");
+      let _held = acquire_at(path.clone(), Duration::ZERO).unwrap();
+      let started = Instant::now();
+      let err = acquire_at(path.clone(), Duration::from_millis(300)).err().expect("the lock is held");
+      assert!(started.elapsed() >= Duration::from_millis(300));
+      let expected = format!("waiting for another labtohub run (pid {}) to finish", std::process::id());
+      assert!(err.to_string().contains(&expected), "{}", err);
+      let err = acquire_at(path, Duration::ZERO).err().expect("the lock is held");
+      assert!(err.to_string().contains("pass --wait-for-lock"), "{}", err);
+   }
+
+   #[test]
+   fn stale_lock_is_reclaimed() {
+      let path = scratch_lock("stale");
+      let mut exited = Command::new("true").spawn().unwrap();
+      exited.wait().unwrap();
+      fs::write(&path, format!("{}\n", exited.id())).unwrap();
+      let lock = acquire_at(path.clone(), Duration::ZERO).unwrap();
+      assert_eq!(holder(&path), Some(std::process::id()));
+      drop(lock);
+      assert!(!path.exists());
+   }
+}
@@ -0,0 +1,185 @@
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Options, Resolve};
+use crate::error::LabtohubError;
+use crate::output;
+use crate::{ensure_full_history, history, included_paths, run_output, worktree_dir};
+
+/// Contents of `path` at `rev`, or `None` when the path does not exist there.
+fn blob_at(rev: &str, path: &str) -> Result<Option<Vec<u8>>> {
+   let output = output::git_command()
+      .args(["show", &format!("{}:{}", rev, path)])
+      .output()?;
+   Ok(output.status.success().then_some(output.stdout))
+}
+
+/// `git ls-files` of the worktree index (origin's tree after the overwrite) for `pathspecs`.
+fn listed(pathspecs: &[String]) -> Result<Vec<String>> {
+   let mut args = vec!["-C", worktree_dir(), "ls-files", "--"];
+   args.extend(pathspecs.iter().map(String::as_str));
+   Ok(run_output("git", &args)?
+      .lines()
+      .map(|line| line.to_string())
+      .collect())
+}
+
+/// Synced paths in the worktree index matching any of the `--merge-path` globs. Under
+/// `--include` the index also holds github's other files, which are not merged.
+fn paths_matching(options: &Options) -> Result<Vec<String>> {
+   let globs = options
+      .merge_paths
+      .iter()
+      .map(|glob| format!(":(glob){}", glob))
+      .collect::<Vec<_>>();
+   let included = included_paths(options).into_iter().map(String::from).collect::<Vec<_>>();
+   let included = listed(&included)?.into_iter().collect::<HashSet<_>>();
+   Ok(listed(&globs)?
+      .into_iter()
+      .filter(|path| included.contains(path))
+      .collect())
+}
+
+/// Which origin commit the destination's copy of the files was last synced from.
+fn merge_base(options: &Options) -> Result<Option<String>> {
+   if let Some(last) = history::last_sync(&options.dest_branch)? {
+      return Ok(Some(last.source_sha));
+   }
+   Ok(run_output("git", &["merge-base", &options.dest_ref(), &options.source_ref()]).ok())
+}
+
+struct Scratch {
+   dir: PathBuf,
+}
+
+impl Scratch {
+   fn new() -> Result<Self> {
+      let dir = std::env::temp_dir().join(format!("labtohub-merge-{}", std::process::id()));
+      fs::create_dir_all(&dir)?;
+      Ok(Scratch { dir })
+   }
+
+   fn write(&self, name: &str, contents: &[u8]) -> Result<PathBuf> {
+      let path = self.dir.join(name);
+      fs::write(&path, contents)?;
+      Ok(path)
+   }
+}
+
+impl Drop for Scratch {
+   fn drop(&mut self) {
+      let _ = fs::remove_dir_all(&self.dir);
+   }
+}
+
+/// The side `--merge-path` conflicts are resolved in favour of, if any.
+/// `--merge-strategy-option` biases these merges too unless they are resolved explicitly.
+fn resolution(options: &Options) -> Option<Resolve> {
+   options.merge_path_resolve.or(options.merge_strategy_option)
+}
+
+/// `git merge-file` printing the merge of github's copy (`dest`) and origin's (`source`).
+fn merge_file_args(dest: &str, source: &str, resolve: Option<Resolve>) -> Vec<String> {
+   let mut args = ["merge-file", "-p", "-L", dest, "-L", "base", "-L", source].map(String::from).to_vec();
+   if let Some(resolve) = resolve {
+      args.push(format!("--{}", resolve.as_str()));
+   }
+   args
+}
+
+/// Three-way merges the `--merge-path` files instead of taking origin's copy wholesale.
+/// Runs after the overwrite, so the worktree already holds origin's version of each file.
+pub fn merge_paths(options: &Options) -> Result<()> {
+   if options.merge_paths.is_empty() {
+      return Ok(());
+   }
+   ensure_full_history(options, "--merge-path")?;
+   let dest = options.dest_ref();
+   let source = options.source_ref();
+   let base = merge_base(options)?;
+   let resolve = resolution(options);
+   match &base {
+      Some(base) => say!("Merging --merge-path files against base {}...", base),
+      None => say!("Merging --merge-path files (no common base found; using an empty base)..."),
+   }
+
+   let scratch = Scratch::new()?;
+   let mut conflicted = Vec::new();
+
+   for path in paths_matching(options)? {
+      // Paths only origin has are simply taken from origin.
+      let Some(ours) = blob_at(&dest, &path)? else {
+         continue;
+      };
+      let theirs = blob_at(&source, &path)?.unwrap_or_default();
+      if ours == theirs {
+         continue;
+      }
+      let ancestor = match &base {
+         Some(base) => blob_at(base, &path)?.unwrap_or_default(),
+         None => Vec::new(),
+      };
+
+      let ours_file = scratch.write("ours", &ours)?;
+      let base_file = scratch.write("base", &ancestor)?;
+      let theirs_file = scratch.write("theirs", &theirs)?;
+      let output = output::git_command()
+         .args(merge_file_args(&dest, &source, resolve))
+         .arg(&ours_file)
+         .arg(&base_file)
+         .arg(&theirs_file)
+         .output()?;
+      // merge-file exits with the number of conflicts, or a negative value on error.
+      match output.status.code() {
+         Some(0) => {}
+         Some(code) if code > 0 => conflicted.push(path.clone()),
+         _ => bail!("git merge-file failed for '{}'", path),
+      }
+      fs::write(Path::new(worktree_dir()).join(&path), &output.stdout)?;
+      say!("  merged {}", path);
+   }
+
+   if !conflicted.is_empty() {
+      say!("Merge conflicts in --merge-path files:");
+      for path in &conflicted {
+         say!("  {}", path);
+      }
+      return Err(LabtohubError::MergeConflict { paths: conflicted }.into());
+   }
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn options(args: &[&str]) -> Options {
+      let argv = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+      crate::cli::parse_args(&argv).expect("test arguments parse")
+   }
+
+   #[test]
+   fn strategy_option_reaches_merge_file() {
+      let options = options(&["--merge-path", "*.md", "--merge-strategy-option", "theirs"]);
+      assert_eq!(
+         merge_file_args("github/main", "origin/main", resolution(&options)),
+         ["merge-file", "-p", "-L", "github/main", "-L", "base", "-L", "origin/main", "--theirs"]
+      );
+      assert!(!merge_file_args("github/main", "origin/main", None).iter().any(|arg| arg.starts_with("--")));
+   }
+
+   #[test]
+   fn merge_path_resolve_wins_over_strategy_option() {
+      let options = options(&[
+         "--merge-path",
+         "*.md",
+         "--merge-strategy-option",
+         "theirs",
+         "--merge-path-resolve",
+         "ours",
+      ]);
+      assert_eq!(resolution(&options), Some(Resolve::Ours));
+   }
+}
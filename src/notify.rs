@@ -0,0 +1,48 @@
+use anyhow::{bail, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::github::curl_quote;
+use crate::{redact_url, report};
+
+/// POSTs `body` as JSON to `url` with curl. The URL goes through curl's config on stdin, so
+/// a token in it never shows up in the process list.
+fn post(url: &str, body: &str) -> Result<()> {
+   let config = [
+      format!("url = {}", curl_quote(url)),
+      format!("header = {}", curl_quote("Content-Type: application/json")),
+      format!("data-binary = {}", curl_quote(body)),
+   ]
+   .join("\n");
+   let mut child = Command::new("curl")
+      .args(["--silent", "--show-error", "--fail", "--max-time", "30", "--config", "-"])
+      .stdin(Stdio::piped())
+      .stdout(Stdio::null())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|_| anyhow::anyhow!("curl is not installed or not on PATH"))?;
+   child
+      .stdin
+      .take()
+      .expect("curl stdin is piped")
+      .write_all(config.as_bytes())?;
+   let output = child.wait_with_output()?;
+   if !output.status.success() {
+      bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+   }
+   Ok(())
+}
+
+/// `--notify-on-failure-url`: tells `url` that the run failed, in which phase and why, along
+/// with the settings it resolved. It runs just before the process exits with the error, so a
+/// notification that cannot be sent only warns.
+pub fn failure(url: &str, err: &anyhow::Error) {
+   say!("Sending the failure notification (--notify-on-failure-url)...");
+   if let Err(notify_err) = post(url, &report::failure_json(err)) {
+      say!(
+         "Warning: could not send the failure notification to {}: {:#}",
+         redact_url(url),
+         notify_err
+      );
+   }
+}
@@ -0,0 +1,255 @@
+use std::env;
+use std::io::{stderr, stdout, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// When set, stdout is reserved for machine-readable output and progress goes to stderr.
+static MACHINE_STDOUT: AtomicBool = AtomicBool::new(false);
+
+pub fn reserve_stdout() {
+   MACHINE_STDOUT.store(true, Ordering::Relaxed);
+}
+
+pub fn stdout_reserved() -> bool {
+   MACHINE_STDOUT.load(Ordering::Relaxed)
+}
+
+/// When set, git's own progress, summaries and hints are silenced (`--quiet-git`).
+static QUIET_GIT: AtomicBool = AtomicBool::new(false);
+
+/// Git subcommands that accept `--quiet`; for `worktree` only `worktree add` does.
+const QUIETABLE: &[&str] = &["fetch", "push", "switch", "checkout", "merge", "commit", "worktree"];
+
+/// Advice git prints as `hint:` lines, turned off through the environment so it applies to
+/// every git invocation, including ones git makes itself.
+const ADVICE_KEYS: &[&str] = &[
+   "advice.pushUpdateRejected",
+   "advice.detachedHead",
+   "advice.statusHints",
+   "advice.setUpstreamFailure",
+];
+
+pub fn quiet_git() {
+   QUIET_GIT.store(true, Ordering::Relaxed);
+}
+
+/// `args` for a git command, with `--quiet` inserted after the subcommand under `--quiet-git`
+/// and the `--git-dir` in front of main-clone commands.
+pub fn git_args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+   let mut args = git_dir_args(args);
+   if !QUIET_GIT.load(Ordering::Relaxed) {
+      return args;
+   }
+   // Skip `-C <path>` so a path is never mistaken for the subcommand.
+   let mut index = 0;
+   while index < args.len() && args[index].starts_with('-') {
+      index += if args[index] == "-C" || args[index] == "-c" { 2 } else { 1 };
+   }
+   if let Some(&subcommand) = args.get(index) {
+      if subcommand == "worktree" {
+         if args.get(index + 1) == Some(&"add") {
+            args.insert(index + 2, "--quiet");
+         }
+      } else if QUIETABLE.contains(&subcommand) {
+         args.insert(index + 1, "--quiet");
+      }
+   }
+   args
+}
+
+/// `--git-dir=<path>` for the main clone's git commands under `--git-dir`, set once for the run.
+static GIT_DIR: OnceLock<String> = OnceLock::new();
+
+pub fn git_dir(path: &str) {
+   let _ = GIT_DIR.set(format!("--git-dir={}", path));
+}
+
+/// `args` with `--git-dir` in front under `--git-dir`. A command that starts with `-C <path>`
+/// runs in a worktree, which finds its repository through its own `.git` file, so it is left
+/// alone.
+pub fn git_dir_args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+   let mut full = Vec::with_capacity(args.len() + 1);
+   if let Some(flag) = GIT_DIR.get().filter(|_| args.first() != Some(&"-C")) {
+      full.push(flag.as_str());
+   }
+   full.extend_from_slice(args);
+   full
+}
+
+/// A `git` command for the main clone, with `--git-dir` already given under `--git-dir`.
+/// Commands in a worktree use a plain `Command::new("git")` with `-C`.
+pub fn git_command() -> Command {
+   let mut command = Command::new("git");
+   command.args(GIT_DIR.get());
+   command
+}
+
+/// The `--ssh-command` git connects with, set once for the whole run.
+static SSH_COMMAND: OnceLock<String> = OnceLock::new();
+
+pub fn ssh_command(command: &str) {
+   let _ = SSH_COMMAND.set(command.to_string());
+}
+
+/// Environment for git commands: `GIT_SSH_COMMAND` under `--ssh-command`, and advice switched
+/// off under `--quiet-git`. Only git's child processes get it, never labtohub's own environment.
+pub fn git_env() -> Vec<(String, String)> {
+   let mut env = Vec::new();
+   if let Some(command) = SSH_COMMAND.get() {
+      env.push(("GIT_SSH_COMMAND".to_string(), command.clone()));
+   }
+   if !QUIET_GIT.load(Ordering::Relaxed) {
+      return env;
+   }
+   // Newer git reads GIT_ADVICE directly; older versions take the config entries below.
+   env.push(("GIT_ADVICE".to_string(), "0".to_string()));
+   env.push(("GIT_CONFIG_COUNT".to_string(), ADVICE_KEYS.len().to_string()));
+   for (index, key) in ADVICE_KEYS.iter().enumerate() {
+      env.push((format!("GIT_CONFIG_KEY_{}", index), key.to_string()));
+      env.push((format!("GIT_CONFIG_VALUE_{}", index), "false".to_string()));
+   }
+   env
+}
+
+/// Where child processes should write their stdout.
+pub fn child_stdout() -> Stdio {
+   if stdout_reserved() {
+      Stdio::from(stderr())
+   } else {
+      Stdio::inherit()
+   }
+}
+
+/// When set, progress overwrites a single status line instead of printing one line per step.
+static ONELINE: AtomicBool = AtomicBool::new(false);
+/// Whether the status line has text on it that no newline has ended yet.
+static STATUS_OPEN: AtomicBool = AtomicBool::new(false);
+
+fn progress_is_terminal() -> bool {
+   if stdout_reserved() {
+      stderr().is_terminal()
+   } else {
+      stdout().is_terminal()
+   }
+}
+
+/// Switches to `--progress oneline` if the progress stream can redraw a line; pipes, log
+/// files and `TERM=dumb` keep the plain one-line-per-step output. Returns whether it did.
+pub fn progress_oneline() -> bool {
+   let dumb = env::var("TERM").map_or(true, |term| term.is_empty() || term == "dumb");
+   if dumb || !progress_is_terminal() {
+      return false;
+   }
+   ONELINE.store(true, Ordering::Relaxed);
+   true
+}
+
+fn write_progress(text: &str) {
+   if stdout_reserved() {
+      eprint!("{}", text);
+      let _ = stderr().flush();
+   } else {
+      print!("{}", text);
+      let _ = stdout().flush();
+   }
+}
+
+/// Ends the status line so whatever is printed next starts on a fresh line.
+pub fn end_status_line() {
+   if STATUS_OPEN.swap(false, Ordering::Relaxed) {
+      write_progress("\n");
+   }
+}
+
+/// Prints one progress message. In oneline mode ordinary steps replace each other on the
+/// status line, while warnings, notes and indented details stay on lines of their own.
+pub fn say(text: &str) {
+   let lasting = text.starts_with(char::is_whitespace)
+      || text.starts_with("Warning")
+      || text.starts_with("Note")
+      || text.contains('\n');
+   if !ONELINE.load(Ordering::Relaxed) || lasting {
+      end_status_line();
+      write_progress(&format!("{}\n", text));
+      return;
+   }
+   let width = env::var("COLUMNS")
+      .ok()
+      .and_then(|columns| columns.parse::<usize>().ok())
+      .unwrap_or(80);
+   // One column short of the width, so the cursor never wraps onto a new line.
+   let line = text.chars().take(width.saturating_sub(1)).collect::<String>();
+   write_progress(&format!("\r\x1b[K{}", line));
+   STATUS_OPEN.store(true, Ordering::Relaxed);
+}
+
+/// Human-readable progress output; moves to stderr when stdout is reserved.
+macro_rules! say {
+   ($($arg:tt)*) => {
+      $crate::output::say(&format!($($arg)*))
+   };
+}
+
+fn escape_json(value: &str) -> String {
+   let mut out = String::with_capacity(value.len() + 2);
+   out.push('"');
+   for c in value.chars() {
+      match c {
+         '"' => out.push_str("\\\""),
+         '\\' => out.push_str("\\\\"),
+         '\n' => out.push_str("\\n"),
+         '\r' => out.push_str("\\r"),
+         '\t' => out.push_str("\\t"),
+         c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+         c => out.push(c),
+      }
+   }
+   out.push('"');
+   out
+}
+
+/// A flat JSON object built in insertion order.
+#[derive(Debug, Default)]
+pub struct JsonObject {
+   fields: Vec<(String, String)>,
+}
+
+impl JsonObject {
+   pub fn new() -> Self {
+      JsonObject::default()
+   }
+
+   pub fn string(mut self, key: &str, value: &str) -> Self {
+      self.fields.push((key.to_string(), escape_json(value)));
+      self
+   }
+
+   pub fn optional_string(self, key: &str, value: Option<&str>) -> Self {
+      match value {
+         Some(value) => self.string(key, value),
+         None => self.raw(key, "null"),
+      }
+   }
+
+   pub fn strings(self, key: &str, values: &[String]) -> Self {
+      let items = values.iter().map(|value| escape_json(value)).collect::<Vec<_>>();
+      self.raw(key, &format!("[{}]", items.join(",")))
+   }
+
+   /// Inserts an already-encoded JSON value.
+   pub fn raw(mut self, key: &str, value: &str) -> Self {
+      self.fields.push((key.to_string(), value.to_string()));
+      self
+   }
+
+   pub fn render(&self) -> String {
+      let body = self
+         .fields
+         .iter()
+         .map(|(key, value)| format!("{}:{}", escape_json(key), value))
+         .collect::<Vec<_>>()
+         .join(",");
+      format!("{{{}}}", body)
+   }
+}
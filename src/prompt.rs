@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use dialoguer::{Confirm, Input, Select};
+use std::env;
+use std::io::{stderr, stdin, BufRead, IsTerminal, Write};
+
+/// How a question can be put to the user.
+enum Terminal {
+   /// A full terminal: dialoguer's interactive widgets.
+   Rich,
+   /// A terminal that cannot redraw lines (`TERM=dumb`, or stderr redirected): plain questions
+   /// answered with a line of input.
+   Plain,
+   /// Nobody to ask.
+   None,
+}
+
+fn terminal() -> Terminal {
+   crate::output::end_status_line();
+   if !stdin().is_terminal() {
+      return Terminal::None;
+   }
+   let dumb = env::var("TERM").map_or(true, |term| term.is_empty() || term == "dumb");
+   if dumb || !stderr().is_terminal() {
+      return Terminal::Plain;
+   }
+   Terminal::Rich
+}
+
+fn no_terminal(question: &str, hint: &str) -> anyhow::Error {
+   anyhow::anyhow!("Cannot ask \"{}\" without a terminal; {}", question, hint)
+}
+
+/// One line typed in answer to `question`, without its line ending. End of input is an error
+/// rather than an empty answer, so a closed stdin never silently picks a default.
+fn read_answer(question: &str) -> Result<String> {
+   eprint!("{} ", question);
+   stderr().flush()?;
+   let mut line = String::new();
+   if stdin().lock().read_line(&mut line)? == 0 {
+      bail!("No answer to \"{}\" (end of input)", question);
+   }
+   Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Asks a yes/no question; without a terminal, fails and points at `--yes`.
+pub fn confirm(question: &str, default: bool) -> Result<bool> {
+   match terminal() {
+      Terminal::Rich => Ok(Confirm::new()
+         .with_prompt(question)
+         .default(default)
+         .interact()?),
+      Terminal::Plain => loop {
+         let choices = if default { "[Y/n]" } else { "[y/N]" };
+         match read_answer(&format!("{} {}", question, choices))?
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+         {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => eprintln!("Please answer y or n."),
+         }
+      },
+      Terminal::None => Err(no_terminal(question, "pass -y/--yes to continue without asking")),
+   }
+}
+
+/// Asks for a line of text starting from `initial`; without a terminal, fails and points at `-m`.
+pub fn input(question: &str, initial: &str, allow_empty: bool) -> Result<String> {
+   match terminal() {
+      Terminal::Rich => Ok(Input::new()
+         .with_prompt(question)
+         .with_initial_text(initial)
+         .allow_empty(allow_empty)
+         .interact_text()?),
+      Terminal::Plain => loop {
+         let prompt = if initial.is_empty() {
+            format!("{}:", question)
+         } else {
+            format!("{} [{}]:", question, initial)
+         };
+         let answer = read_answer(&prompt)?;
+         let answer = answer.trim();
+         if !answer.is_empty() {
+            return Ok(answer.to_string());
+         }
+         if !initial.is_empty() {
+            return Ok(initial.to_string());
+         }
+         if allow_empty {
+            return Ok(String::new());
+         }
+      },
+      Terminal::None => Err(no_terminal(question, "pass it with -m, -F or on stdin")),
+   }
+}
+
+/// Prints `text` and waits for Enter; without a terminal, returns at once.
+pub fn pause(text: &str) -> Result<()> {
+   match terminal() {
+      Terminal::Rich | Terminal::Plain => {
+         read_answer(&format!("{} Press Enter to continue.", text))?;
+         Ok(())
+      }
+      Terminal::None => Ok(()),
+   }
+}
+
+/// Lets the user pick one of `items` and returns its index.
+pub fn select<T: AsRef<str>>(question: &str, items: &[T], default: usize) -> Result<usize> {
+   match terminal() {
+      Terminal::Rich => {
+         let labels = items.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+         Ok(Select::new()
+            .with_prompt(question)
+            .items(&labels)
+            .default(default)
+            .interact()?)
+      }
+      Terminal::Plain => {
+         eprintln!("{}", question);
+         for (index, item) in items.iter().enumerate() {
+            eprintln!("  {}) {}", index + 1, item.as_ref());
+         }
+         loop {
+            let answer = read_answer(&format!("Choice [{}]:", default + 1))?;
+            let answer = answer.trim();
+            if answer.is_empty() {
+               return Ok(default);
+            }
+            match answer.parse::<usize>() {
+               Ok(number) if (1..=items.len()).contains(&number) => return Ok(number - 1),
+               _ => eprintln!("Please enter a number from 1 to {}.", items.len()),
+            }
+         }
+      }
+      Terminal::None => Err(no_terminal(question, "pass -y/--yes or the matching option")),
+   }
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+
+use crate::cli::Options;
+use crate::github;
+use crate::output::JsonObject;
+use crate::run_output;
+
+/// The JSON the releases API is sent: the tag names the release and its message is the body.
+fn request_body(options: &Options, tag: &str, body: &str) -> String {
+   JsonObject::new()
+      .string("tag_name", tag)
+      .string("name", tag)
+      .string("body", body)
+      .raw("draft", &options.draft.to_string())
+      .raw("prerelease", &options.prerelease.to_string())
+      .render()
+}
+
+fn create_release(options: &Options, tag: &str) -> Result<String> {
+   let body = run_output(
+      "git",
+      &["tag", "-l", "--format=%(contents:subject)%0a%0a%(contents:body)", tag],
+   )?;
+   let response = github::request(options, "POST", "/releases", &request_body(options, tag, &body))?;
+   Ok(github::json_field(&response, "html_url").unwrap_or_default())
+}
+
+/// `--github-release`: creates a GitHub release for the pushed `--tag`. The tag is already
+/// published by then, so a failure only warns.
+pub fn create(options: &Options) {
+   let Some(tag) = options.tag.as_deref().filter(|_| options.github_release) else {
+      return;
+   };
+   say!("Creating GitHub release '{}' (--github-release)...", tag);
+   match create_release(options, tag) {
+      Ok(url) if url.is_empty() => say!("Created GitHub release '{}'.", tag),
+      Ok(url) => say!("Created GitHub release '{}': {}", tag, url),
+      Err(err) => say!(
+         "Warning: could not create the GitHub release for '{}': {:#}. The tag was pushed; \
+          create the release by hand.",
+         tag,
+         err
+      ),
+   }
+}
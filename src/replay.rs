@@ -0,0 +1,93 @@
+use anyhow::{bail, Result};
+
+use crate::cli::Options;
+use crate::sign;
+use crate::{
+   apply_diff, date_env, ensure_full_history, origin_log_base, run_git_in_env, run_output, short_sha,
+   stage_worktree, worktree_dir,
+};
+
+/// `--replay`: the origin commits to replay, oldest first. These are the commits since the last
+/// recorded sync, or since the destination branch on the first run, following the first
+/// parent so each merge is replayed as the change it brought in.
+pub fn commits(options: &Options) -> Result<Vec<String>> {
+   ensure_full_history(options, "--replay")?;
+   let base = origin_log_base(options)?;
+   let range = format!("{}..{}", base, options.source_ref());
+   let listing = run_output("git", &["rev-list", "--reverse", "--first-parent", &range])?;
+   let commits = listing.lines().map(str::to_string).collect::<Vec<_>>();
+   if let Some(first) = commits.first() {
+      if run_output("git", &["rev-parse", "--verify", "--quiet", &format!("{}^", first)]).is_err() {
+         bail!(
+            "{} shares no history with {} and no earlier sync is recorded, so there is no commit to \
+             replay from; run one sync without --replay first, and later --replay runs start from it",
+            options.source_ref(),
+            options.dest_ref()
+         );
+      }
+   }
+   let shown = if base == options.dest_ref() { base.as_str() } else { short_sha(&base) };
+   say!(
+      "Replaying {} origin commit(s) from {}..{} (--replay).",
+      commits.len(),
+      shown,
+      options.source_ref()
+   );
+   Ok(commits)
+}
+
+/// `--dry-run` under `--replay`: stages what the whole replay would change, in one go.
+pub fn preview(options: &Options, commits: &[String]) -> Result<()> {
+   let Some(first) = commits.first() else {
+      return Ok(());
+   };
+   let base = format!("{}^", first);
+   if !apply_diff(options, &base, &options.source_ref())? {
+      bail!(
+         "The origin commits since {} do not apply cleanly onto {}; run without --replay to bring it \
+          in line first",
+         short_sha(&run_output("git", &["rev-parse", &base])?),
+         options.dest_ref()
+      );
+   }
+   Ok(())
+}
+
+/// Applies each of `commits` to the worktree and commits it with its own message and author,
+/// so the destination gets origin's history rather than one commit. A commit that changes
+/// nothing here is skipped. Returns whether anything was committed.
+pub fn commit_all(options: &Options, commits: &[String], date: Option<&str>) -> Result<bool> {
+   let mut replayed = 0;
+   for (index, commit) in commits.iter().enumerate() {
+      let subject = run_output("git", &["log", "-1", "--format=%s", commit])?;
+      say!("Replaying {} {} ({}/{})...", short_sha(commit), subject, index + 1, commits.len());
+      if !apply_diff(options, &format!("{}^", commit), commit)? {
+         bail!(
+            "Origin commit {} ('{}') does not apply cleanly onto {}, which differs from origin where it \
+             changes files. Run one sync without --replay to bring {} in line; later --replay runs \
+             start from the commit it records",
+            short_sha(commit),
+            subject,
+            options.dest_ref(),
+            options.dest_ref()
+         );
+      }
+      if !stage_worktree(options)? {
+         continue;
+      }
+      let mut args = vec!["commit", "--quiet", "-C", commit.as_str()];
+      args.extend(sign::args(options));
+      let committed = run_git_in_env(worktree_dir(), &args, &date_env(date));
+      sign::explain(options, &format!("the replay of {}", short_sha(commit)), committed)?;
+      replayed += 1;
+   }
+   if replayed < commits.len() {
+      say!(
+         "Replayed {} of {} origin commit(s); the rest changed nothing on {}.",
+         replayed,
+         commits.len(),
+         options.dest_ref()
+      );
+   }
+   Ok(replayed > 0)
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::cli::Options;
+use crate::{run_output, worktree_dir};
+
+fn git_config(key: &str) -> Option<String> {
+   run_output("git", &["config", "--get", key]).ok().filter(|value| !value.is_empty())
+}
+
+/// `gpg.format`, which picks how git signs: `openpgp` (the default), `ssh` or `x509`.
+pub fn format() -> String {
+   git_config("gpg.format").unwrap_or_else(|| "openpgp".to_string())
+}
+
+/// For `--sign`: makes sure git has a key to sign with in the configured `gpg.format`, so a
+/// missing key is explained up front instead of by git failing after the build.
+pub fn validate(options: &Options) -> Result<()> {
+   if !options.sign {
+      return Ok(());
+   }
+   check_signing_key(git_config)
+}
+
+/// `validate`'s check, with the git configuration read through `config`.
+fn check_signing_key(config: impl Fn(&str) -> Option<String>) -> Result<()> {
+   let key = config("user.signingkey");
+   match config("gpg.format").as_deref().unwrap_or("openpgp") {
+      "ssh" => {
+         if key.is_none() && config("gpg.ssh.defaultKeyCommand").is_none() {
+            bail!(
+               "--sign: gpg.format is ssh, but neither user.signingkey nor gpg.ssh.defaultKeyCommand \
+                is set; point it at your key, e.g. `git config user.signingkey ~/.ssh/id_ed25519.pub`"
+            );
+         }
+      }
+      "openpgp" => {
+         if key.is_some() {
+            return Ok(());
+         }
+         let program = config("gpg.program").unwrap_or_else(|| "gpg".to_string());
+         let secret_keys = Command::new(&program)
+            .args(["--list-secret-keys", "--with-colons"])
+            .stderr(Stdio::null())
+            .output();
+         let has_key = secret_keys.is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+               .lines()
+               .any(|line| line.starts_with("sec:"))
+         });
+         if !has_key {
+            bail!(
+               "--sign: user.signingkey is not set and {} has no secret key; create one with \
+                `gpg --full-generate-key`, or sign with SSH: `git config gpg.format ssh` and \
+                `git config user.signingkey ~/.ssh/id_ed25519.pub`",
+               program
+            );
+         }
+      }
+      // gpgsm finds its certificate from the committer identity; nothing to check up front.
+      "x509" => {}
+      other => bail!("--sign: unknown gpg.format '{}'; git supports openpgp, ssh and x509", other),
+   }
+   Ok(())
+}
+
+/// `-S` for the commits and merges labtohub makes under `--sign`, so git signs them with
+/// `user.signingkey` in the configured format.
+pub fn args(options: &Options) -> &'static [&'static str] {
+   if options.sign {
+      &["-S"]
+   } else {
+      &[]
+   }
+}
+
+/// Why signing `what` (e.g. "tag 'v1'") failed, in terms of the configured format.
+pub fn failure(what: &str) -> String {
+   match format().as_str() {
+      "ssh" => format!(
+         "Could not sign {} with SSH; check that user.signingkey names a key ssh-keygen can use \
+          (the public key file, with the private key next to it or in ssh-agent)",
+         what
+      ),
+      _ => format!(
+         "Could not sign {}; check that user.signingkey is set and gpg can use it \
+          (e.g. `echo test | gpg --clearsign`)",
+         what
+      ),
+   }
+}
+
+/// `result` of a git command that signs `what` under `--sign`, with `failure` added to its
+/// error then, since a signing problem is the likely cause; otherwise `result` as is.
+pub fn explain<T>(options: &Options, what: &str, result: Result<T>) -> Result<T> {
+   if options.sign {
+      result.with_context(|| failure(what))
+   } else {
+      result
+   }
+}
+
+/// With SSH signing and `gpg.ssh.allowedSignersFile` set, checks that what `verify_args`
+/// verifies (`tag -v <name>`, say) does, warning when the key is not an allowed signer,
+/// since everyone verifying it would see that.
+pub fn verify_ssh_signature(options: &Options, what: &str, verify_args: &[&str]) {
+   if !options.sign || format() != "ssh" || git_config("gpg.ssh.allowedSignersFile").is_none() {
+      return;
+   }
+   let verified = Command::new("git")
+      .args(["-C", worktree_dir()])
+      .args(verify_args)
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .status()
+      .is_ok_and(|status| status.success());
+   if !verified {
+      say!(
+         "Warning: {} is signed, but its key is not in gpg.ssh.allowedSignersFile, so it does \
+          not verify there.",
+         what
+      );
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   /// A stand-in for `git config --get`, answering from `entries`.
+   fn fake_git(entries: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+      move |key| {
+         entries
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.to_string())
+      }
+   }
+
+   #[test]
+   fn ssh_signing_needs_a_key_or_key_command() {
+      assert!(check_signing_key(fake_git(&[
+         ("gpg.format", "ssh"),
+         ("user.signingkey", "~/.ssh/id_ed25519.pub"),
+         ("gpg.ssh.allowedSignersFile", "~/.ssh/allowed_signers"),
+      ]))
+      .is_ok());
+      assert!(check_signing_key(fake_git(&[
+         ("gpg.format", "ssh"),
+         ("gpg.ssh.defaultKeyCommand", "ssh-add -L"),
+      ]))
+      .is_ok());
+      let err = check_signing_key(fake_git(&[("gpg.format", "ssh")])).unwrap_err();
+      assert!(err.to_string().contains("gpg.format is ssh"), "{}", err);
+   }
+
+   #[test]
+   fn openpgp_signing_needs_a_key_or_a_secret_key() {
+      assert!(check_signing_key(fake_git(&[("user.signingkey", "0xDEADBEEF")])).is_ok());
+      let explicit = fake_git(&[("gpg.format", "openpgp"), ("user.signingkey", "0xDEADBEEF")]);
+      assert!(check_signing_key(explicit).is_ok());
+      // `true` lists no secret keys.
+      let err = check_signing_key(fake_git(&[("gpg.program", "true")])).unwrap_err();
+      assert!(err.to_string().contains("true has no secret key"), "{}", err);
+      let err = check_signing_key(fake_git(&[("gpg.format", "pgp")])).unwrap_err();
+      assert!(err.to_string().contains("unknown gpg.format 'pgp'"), "{}", err);
+   }
+
+   #[test]
+   fn sign_adds_s_to_commits_without_a_tag() {
+      let options = crate::cli::parse_args(&["--sign".to_string()]).unwrap();
+      assert_eq!(args(&options), ["-S"]);
+      assert!(args(&Options::default()).is_empty());
+   }
+}
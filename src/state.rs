@@ -0,0 +1,142 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::run_output;
+
+const STATE_FILE: &str = "labtohub-state";
+
+/// How far an interrupted sync got. Each phase keeps what `--resume` needs to carry on
+/// from there.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Phase {
+   /// Origin and github were fetched at the recorded SHAs.
+   Fetched,
+   /// The worktree was built and staged as `tree`, hooks and all.
+   Built { tree: String },
+   /// The content commit(s) are on the branch, ending at `content_sha`.
+   Committed { content_sha: String },
+   /// The staging branch is at `staging_sha`, ready to push; `result` is how publishing it
+   /// reports the sync.
+   Merged { staging_sha: String, result: String },
+}
+
+/// A sync that has not been confirmed as pushed yet. `--resume` skips the phases it
+/// finished, as long as the inputs match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncState {
+   pub push_ref: String,
+   pub source_sha: String,
+   /// The destination tip the sync builds on; empty when the branch is being created.
+   pub dest_sha: String,
+   pub branch: String,
+   pub message: String,
+   pub phase: Phase,
+}
+
+impl SyncState {
+   fn to_text(&self) -> String {
+      let phase = match &self.phase {
+         Phase::Fetched => "phase fetched".to_string(),
+         Phase::Built { tree } => format!("phase built\ntree {}", tree),
+         Phase::Committed { content_sha } => format!("phase committed\ncontent {}", content_sha),
+         Phase::Merged { staging_sha, result } => {
+            format!("phase merged\nstaging {}\nresult {}", staging_sha, result)
+         }
+      };
+      // The message goes last since it is the only field that may span lines.
+      format!(
+         "push_ref {}\nsource {}\ndest {}\nbranch {}\n{}\n\n{}\n",
+         self.push_ref, self.source_sha, self.dest_sha, self.branch, phase, self.message
+      )
+   }
+
+   fn from_text(text: &str) -> Option<Self> {
+      let (header, message) = text.split_once("\n\n")?;
+      let field = |name: &str| {
+         header.lines().find_map(|line| {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            (key == name).then(|| value.to_string())
+         })
+      };
+      let phase = match field("phase")?.as_str() {
+         "fetched" => Phase::Fetched,
+         "built" => Phase::Built { tree: field("tree")? },
+         "committed" => Phase::Committed {
+            content_sha: field("content")?,
+         },
+         "merged" => Phase::Merged {
+            staging_sha: field("staging")?,
+            result: field("result")?,
+         },
+         _ => return None,
+      };
+      Some(SyncState {
+         push_ref: field("push_ref")?,
+         source_sha: field("source")?,
+         dest_sha: field("dest")?,
+         branch: field("branch")?,
+         message: message.trim_end().to_string(),
+         phase,
+      })
+   }
+}
+
+/// Kept next to the history log, in the shared git dir.
+fn state_path() -> Result<PathBuf> {
+   let git_dir = run_output("git", &["rev-parse", "--git-common-dir"])?;
+   Ok(PathBuf::from(git_dir).join(STATE_FILE))
+}
+
+pub fn save(state: &SyncState) -> Result<()> {
+   fs::write(state_path()?, state.to_text())?;
+   Ok(())
+}
+
+/// The recorded sync, if any. A file that cannot be parsed is treated as absent.
+pub fn load() -> Result<Option<SyncState>> {
+   match fs::read_to_string(state_path()?) {
+      Ok(text) => Ok(SyncState::from_text(&text)),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+      Err(err) => Err(err.into()),
+   }
+}
+
+pub fn clear() -> Result<()> {
+   match fs::remove_file(state_path()?) {
+      Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+      _ => Ok(()),
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn every_phase_round_trips() {
+      let phases = [
+         Phase::Fetched,
+         Phase::Built { tree: "4b825dc".to_string() },
+         Phase::Committed {
+            content_sha: "c0ffee1".to_string(),
+         },
+         Phase::Merged {
+            staging_sha: "5eed5ed".to_string(),
+            result: "created".to_string(),
+         },
+      ];
+      for phase in phases {
+         let state = SyncState {
+            push_ref: "refs/heads/main".to_string(),
+            source_sha: "a1".to_string(),
+            dest_sha: String::new(),
+            branch: "sync-docs".to_string(),
+            message: "Sync docs\n\nTicket: OPS-1".to_string(),
+            phase,
+         };
+         assert_eq!(SyncState::from_text(&state.to_text()), Some(state));
+      }
+      assert_eq!(SyncState::from_text("push_ref x\nphase pushed\n\nmsg\n"), None);
+   }
+}
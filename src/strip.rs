@@ -0,0 +1,186 @@
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+
+use crate::cli::Options;
+use crate::output;
+use crate::run_output;
+
+/// `--strip-prefix`: origin's tree with everything under `prefix/` moved up to the root and
+/// the files outside it left where they are. Built in a throwaway index, like the aggregate
+/// conflict resolution, so neither the repository's nor the worktree's index is touched.
+pub fn stripped_tree(options: &Options, prefix: &str) -> Result<String> {
+   let source = options.source_ref();
+   let prefix = format!("{}/", prefix.trim_matches('/'));
+   let listing = run_output("git", &["ls-tree", "-r", "-z", "--full-tree", &source])?;
+   let entries = rearrange(&listing, &source, &prefix)?;
+
+   let index = std::env::temp_dir().join(format!("labtohub-strip-index-{}", std::process::id()));
+   let result = (|| {
+      let mut child = output::git_command()
+         .args(["update-index", "--add", "-z", "--index-info"])
+         .env("GIT_INDEX_FILE", &index)
+         .stdin(Stdio::piped())
+         .spawn()?;
+      let mut info = Vec::new();
+      for (mode, oid, path, _) in &entries {
+         info.extend(format!("{} {}\t{}\0", mode, oid, path).into_bytes());
+      }
+      child
+         .stdin
+         .take()
+         .expect("update-index stdin is piped")
+         .write_all(&info)?;
+      if !child.wait()?.success() {
+         bail!("Command failed: git update-index --index-info");
+      }
+      let output = output::git_command()
+         .arg("write-tree")
+         .env("GIT_INDEX_FILE", &index)
+         .output()?;
+      if !output.status.success() {
+         bail!("Command failed: git write-tree");
+      }
+      Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+   })();
+   let _ = fs::remove_file(&index);
+   result
+}
+
+/// One entry of origin's tree: mode, object id, the path it ends up at, and the path it had
+/// when the strip moved it.
+type Entry<'a> = (String, String, String, Option<&'a str>);
+
+/// `listing`, `git ls-tree -r -z` of `source`, with the paths under `prefix` (ending in `/`)
+/// moved up to the root. Fails when nothing is under `prefix` or a moved path clashes.
+fn rearrange<'a>(listing: &'a str, source: &str, prefix: &str) -> Result<Vec<Entry<'a>>> {
+   let mut entries = Vec::new();
+   let mut moved = 0;
+   for entry in listing.split('\0').filter(|entry| !entry.is_empty()) {
+      let Some((meta, path)) = entry.split_once('\t') else {
+         bail!("Unexpected ls-tree output: '{}'", entry);
+      };
+      let mut fields = meta.split_whitespace();
+      let (Some(mode), Some(_), Some(oid)) = (fields.next(), fields.next(), fields.next()) else {
+         bail!("Unexpected ls-tree output for '{}'", path);
+      };
+      let (path, from) = match path.strip_prefix(prefix) {
+         Some(stripped) => {
+            moved += 1;
+            (stripped.to_string(), Some(path))
+         }
+         None => (path.to_string(), None),
+      };
+      entries.push((mode.to_string(), oid.to_string(), path, from));
+   }
+   if moved == 0 {
+      bail!("{} has no files under '{}' to strip (--strip-prefix)", source, prefix);
+   }
+
+   // A moved file may not land on a file already at the root, or on either side of one's
+   // directory (`src` the file against `src/x` moved up).
+   let kept = entries
+      .iter()
+      .filter(|(_, _, _, from)| from.is_none())
+      .map(|(_, _, path, _)| path.as_str())
+      .collect::<HashSet<_>>();
+   let kept_dirs = kept
+      .iter()
+      .flat_map(|path| parents(path))
+      .collect::<HashSet<_>>();
+   let mut conflicts = entries
+      .iter()
+      .filter_map(|(_, _, path, from)| {
+         let from = (*from)?;
+         let clashes = kept.contains(path.as_str())
+            || kept_dirs.contains(path.as_str())
+            || parents(path).any(|dir| kept.contains(dir));
+         clashes.then(|| format!("{} -> {}", from, path))
+      })
+      .collect::<Vec<_>>();
+   if !conflicts.is_empty() {
+      conflicts.sort();
+      say!("Stripping '{}' would overwrite files outside it:", prefix);
+      for conflict in &conflicts {
+         say!("  {}", conflict);
+      }
+      bail!(
+         "--strip-prefix {} clashes with {} path(s) outside it in {}",
+         prefix.trim_end_matches('/'),
+         conflicts.len(),
+         source
+      );
+   }
+   Ok(entries)
+}
+
+/// `a/b/c` gives `a` and `a/b`.
+fn parents(path: &str) -> impl Iterator<Item = &str> {
+   path.match_indices('/').map(move |(index, _)| &path[..index])
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   /// `git ls-tree -r -z` output for `paths`, all regular files.
+   fn listing(paths: &[&str]) -> String {
+      paths
+         .iter()
+         .map(|path| format!("100644 blob 0123456789abcdef0123456789abcdef01234567\t{}\0", path))
+         .collect()
+   }
+
+   fn placed(entries: &[Entry]) -> Vec<(String, Option<String>)> {
+      entries
+         .iter()
+         .map(|(_, _, path, from)| (path.clone(), from.map(String::from)))
+         .collect()
+   }
+
+   #[test]
+   fn two_level_prefix_moves_up_and_keeps_the_rest() {
+      let listing =
+         listing(&["README.md", "projects/bar/x", "projects/foo/src/lib.rs", "projects/foo/Cargo.toml"]);
+      let entries = rearrange(&listing, "origin/main", "projects/foo/").unwrap();
+      assert_eq!(
+         placed(&entries),
+         [
+            ("README.md".to_string(), None),
+            ("projects/bar/x".to_string(), None),
+            ("src/lib.rs".to_string(), Some("projects/foo/src/lib.rs".to_string())),
+            ("Cargo.toml".to_string(), Some("projects/foo/Cargo.toml".to_string())),
+         ]
+      );
+   }
+
+   #[test]
+   fn moved_file_landing_on_a_kept_file_clashes() {
+      let listing = listing(&["README.md", "projects/foo/README.md", "projects/foo/a"]);
+      let err = rearrange(&listing, "origin/main", "projects/foo/").unwrap_err();
+      assert!(err.to_string().contains("clashes with 1 path(s)"), "{}", err);
+   }
+
+   #[test]
+   fn moved_file_against_a_kept_directory_clashes_both_ways() {
+      // A moved file where a kept directory is, and a moved directory where a kept file is.
+      let listing = listing(&["docs/guide.md", "src", "projects/foo/docs", "projects/foo/src/x"]);
+      let err = rearrange(&listing, "origin/main", "projects/foo/").unwrap_err();
+      assert!(err.to_string().contains("clashes with 2 path(s)"), "{}", err);
+   }
+
+   #[test]
+   fn prefix_without_files_is_an_error() {
+      let listing = listing(&["README.md", "projects/foobar/x"]);
+      let err = rearrange(&listing, "origin/main", "projects/foo/").unwrap_err();
+      assert!(err.to_string().contains("no files under 'projects/foo/'"), "{}", err);
+   }
+
+   #[test]
+   fn parents_lists_each_enclosing_directory() {
+      assert_eq!(parents("a/b/c").collect::<Vec<_>>(), ["a", "a/b"]);
+      assert_eq!(parents("file").count(), 0);
+   }
+}
@@ -0,0 +1,162 @@
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::cli::Options;
+use crate::{redact_url, run_git_in, run_git_network, run_output, short_sha, worktree_dir};
+
+/// Mode git gives a submodule's entry in the tree it is recorded in.
+const GITLINK_MODE: &str = "160000";
+
+fn join(dir: &str, path: &str) -> String {
+   if dir.is_empty() {
+      path.to_string()
+   } else {
+      format!("{}/{}", dir, path)
+   }
+}
+
+/// A `.gitmodules` URL as git resolves it: `./` and `../` are relative to the URL of the
+/// repository that records the submodule.
+fn resolve_url(base: &str, url: &str) -> String {
+   if !url.starts_with("./") && !url.starts_with("../") {
+      return url.to_string();
+   }
+   let mut base = base.trim_end_matches('/').to_string();
+   let mut rest = url;
+   loop {
+      if let Some(next) = rest.strip_prefix("./") {
+         rest = next;
+      } else if let Some(next) = rest.strip_prefix("../") {
+         // `git@host:group/repo` has its first path segment after the colon, which stays.
+         let cut = base.rfind('/').or_else(|| base.rfind(':').map(|colon| colon + 1)).unwrap_or(0);
+         base.truncate(cut);
+         rest = next;
+      } else {
+         break;
+      }
+   }
+   if base.ends_with(':') {
+      format!("{}{}", base, rest)
+   } else {
+      format!("{}/{}", base, rest)
+   }
+}
+
+/// `(name, path)` of each submodule in `file`, as `git config` reads it.
+fn submodule_paths(file: &Path) -> Result<Vec<(String, String)>> {
+   let file = file.to_string_lossy();
+   let listing = run_output("git", &["config", "-f", &file, "--get-regexp", r"^submodule\..*\.path$"])
+      .unwrap_or_default();
+   Ok(listing
+      .lines()
+      .filter_map(|line| {
+         let (key, path) = line.split_once(' ')?;
+         let name = key.strip_prefix("submodule.")?.strip_suffix(".path")?;
+         Some((name.to_string(), path.to_string()))
+      })
+      .collect())
+}
+
+/// Gets `commit` from the submodule's repository into this one. Asking for the commit
+/// itself is the cheap way, which GitHub and GitLab allow; a server that only serves what
+/// it advertises gets its branches and tags fetched instead.
+fn fetch_commit(url: &str, commit: &str) -> Result<()> {
+   let present = |commit: &str| {
+      run_output("git", &["cat-file", "-e", &format!("{}^{{commit}}", commit)]).is_ok()
+   };
+   if present(commit) {
+      return Ok(());
+   }
+   let by_sha = run_git_network(url, &["fetch", "--quiet", "--no-tags", url, commit]);
+   if by_sha.is_err() || !present(commit) {
+      let refspecs = [
+         "+refs/heads/*:refs/labtohub-submodule/heads/*",
+         "+refs/tags/*:refs/labtohub-submodule/tags/*",
+      ];
+      let mut args = vec!["fetch", "--quiet", "--no-tags", url];
+      args.extend(refspecs);
+      run_git_network(url, &args)?;
+      let refs = run_output("git", &["for-each-ref", "--format=%(refname)", "refs/labtohub-submodule/"])?;
+      for reference in refs.lines() {
+         let _ = run_output("git", &["update-ref", "-d", reference]);
+      }
+      if !present(commit) {
+         bail!("{} does not have submodule commit {}", redact_url(url), commit);
+      }
+   }
+   Ok(())
+}
+
+/// `--vendor-submodules`: replaces each submodule in the worktree with the files of the
+/// commit it records, nested ones included, and drops the `.gitmodules` files, so the
+/// mirror needs none of the submodules' repositories.
+pub fn vendor(options: &Options) -> Result<()> {
+   if !options.vendor_submodules {
+      return Ok(());
+   }
+   let worktree = worktree_dir();
+   let source_url = run_output("git", &["remote", "get-url", &options.source_remote])?;
+   let root = options.dest_subdir.clone().unwrap_or_default();
+   let mut pending = vec![(root, source_url)];
+   let mut vendored = 0;
+   while let Some((dir, base_url)) = pending.pop() {
+      let gitmodules = join(&dir, ".gitmodules");
+      let file = Path::new(worktree).join(&gitmodules);
+      if !file.is_file() {
+         continue;
+      }
+      for (name, path) in submodule_paths(&file)? {
+         let path = join(&dir, &path);
+         let entry = run_output("git", &["-C", worktree, "ls-files", "--stage", "--", &path])?;
+         let mut fields = entry.split_whitespace();
+         let (Some(GITLINK_MODE), Some(commit)) = (fields.next(), fields.next()) else {
+            // Listed in .gitmodules but not recorded in the tree: nothing to vendor.
+            continue;
+         };
+         let commit = commit.to_string();
+         let key = format!("submodule.{}.url", name);
+         let Ok(url) = run_output("git", &["config", "-f", &file.to_string_lossy(), &key]) else {
+            bail!("Submodule '{}' has no url in {}", path, gitmodules);
+         };
+         let url = resolve_url(&base_url, &url);
+         fetch_commit(&url, &commit)?;
+         run_git_in(worktree, &["rm", "--cached", "-q", "-f", "--", &path])?;
+         let on_disk = Path::new(worktree).join(&path);
+         if on_disk.exists() {
+            fs::remove_dir_all(&on_disk)?;
+         }
+         run_git_in(worktree, &["read-tree", &format!("--prefix={}/", path), "-u", &commit])?;
+         say!("Vendored submodule '{}' at {} from {}.", path, short_sha(&commit), redact_url(&url));
+         vendored += 1;
+         pending.push((path, url));
+      }
+      run_git_in(worktree, &["rm", "-q", "-f", "--", &gitmodules])?;
+   }
+   if vendored > 0 {
+      say!("Vendored {} submodule(s) as regular files (--vendor-submodules).", vendored);
+   }
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn absolute_urls_are_kept() {
+      assert_eq!(resolve_url("https://gitlab.example.com/team/app.git", "https://x/lib.git"), "https://x/lib.git");
+      assert_eq!(resolve_url("https://gitlab.example.com/team/app.git", "git@host:lib.git"), "git@host:lib.git");
+   }
+
+   #[test]
+   fn relative_urls_resolve_against_the_recording_repository() {
+      let base = "https://gitlab.example.com/team/app.git";
+      assert_eq!(resolve_url(base, "../lib.git"), "https://gitlab.example.com/team/lib.git");
+      assert_eq!(resolve_url(base, "../../shared/lib.git"), "https://gitlab.example.com/shared/lib.git");
+      assert_eq!(resolve_url(base, "./lib.git"), "https://gitlab.example.com/team/app.git/lib.git");
+      assert_eq!(resolve_url("https://gitlab.example.com/team/app/", "../lib"), "https://gitlab.example.com/team/lib");
+      assert_eq!(resolve_url("git@gitlab.example.com:team/app.git", "../lib.git"), "git@gitlab.example.com:team/lib.git");
+      assert_eq!(resolve_url("git@gitlab.example.com:app.git", "../lib.git"), "git@gitlab.example.com:lib.git");
+   }
+}
@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use std::process::Stdio;
+
+use crate::cli::Options;
+use crate::hooks::HookContext;
+use crate::output;
+use crate::sign;
+use crate::{run_git_in, run_output, worktree_dir};
+
+/// Placeholders `--tag-message-template` understands, each replaced by its value.
+fn placeholders(options: &Options, context: &HookContext) -> Result<Vec<(&'static str, String)>> {
+   let subject = context.message.lines().next().unwrap_or_default().to_string();
+   Ok(vec![
+      ("{message}", context.message.to_string()),
+      ("{subject}", subject),
+      ("{branch}", context.branch.to_string()),
+      ("{source_ref}", options.source_ref()),
+      ("{dest_ref}", options.dest_ref()),
+      ("{source_sha}", run_output("git", &["rev-parse", &options.source_ref()])?),
+      ("{date}", run_output("git", &["log", "-1", "--format=%cI", &options.staging_branch()])?),
+   ])
+}
+
+fn render(template: &str, values: &[(&str, String)]) -> String {
+   values
+      .iter()
+      .fold(template.to_string(), |text, (placeholder, value)| text.replace(placeholder, value))
+}
+
+fn tag_ref(name: &str) -> String {
+   format!("refs/tags/{}", name)
+}
+
+/// The `src:dst` refspec that publishes the tag alongside the branch, when `--tag` is set.
+pub fn push_refspec(options: &Options) -> Option<String> {
+   options
+      .tag
+      .as_ref()
+      .map(|name| format!("{0}:{0}", tag_ref(name)))
+}
+
+/// Fails early on tag names git would reject, before any work is done.
+pub fn validate(options: &Options) -> Result<()> {
+   let Some(name) = &options.tag else {
+      return Ok(());
+   };
+   if run_output("git", &["check-ref-format", &tag_ref(name)]).is_err() {
+      bail!("'{}' is not a valid tag name", name);
+   }
+   if run_output("git", &["rev-parse", "--verify", "--quiet", &tag_ref(name)]).is_ok() {
+      bail!("Tag '{}' already exists locally; choose another --tag or delete it first", name);
+   }
+   Ok(())
+}
+
+/// Creates the annotated (or with `--sign`, GPG-signed) `--tag` on the staging branch.
+pub fn create(options: &Options, context: &HookContext) -> Result<()> {
+   let Some(name) = &options.tag else {
+      return Ok(());
+   };
+   let message = match &options.tag_message_template {
+      Some(template) => render(template, &placeholders(options, context)?),
+      None => context.message.to_string(),
+   };
+   let kind = if options.sign { "-s" } else { "-a" };
+   say!(
+      "Creating {} tag '{}'...",
+      if options.sign { "signed" } else { "annotated" },
+      name
+   );
+   let staging = options.staging_branch();
+   if run_git_in(worktree_dir(), &["tag", kind, "-m", &message, name, &staging]).is_err() {
+      if options.sign {
+         bail!(sign::failure(&format!("tag '{}'", name)));
+      }
+      bail!("Could not create tag '{}'", name);
+   }
+   sign::verify_ssh_signature(options, &format!("tag '{}'", name), &["tag", "-v", name]);
+   Ok(())
+}
+
+/// Drops the tag `create` made when the push did not go through, so a retry can recreate it.
+pub fn discard(options: &Options) {
+   if let Some(name) = &options.tag {
+      let _ = output::git_command()
+         .args(["tag", "-d", name])
+         .stdout(Stdio::null())
+         .stderr(Stdio::null())
+         .status();
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn render_fills_in_every_placeholder() {
+      let values = [("{subject}", "Sync docs".to_string()), ("{source_sha}", "abc123".to_string())];
+      assert_eq!(render("Mirror of {source_sha}: {subject}", &values), "Mirror of abc123: Sync docs");
+      assert_eq!(render("{subject} / {subject}", &values), "Sync docs / Sync docs");
+      // Unknown placeholders are left as written.
+      assert_eq!(render("{version} {subject}", &values), "{version} Sync docs");
+   }
+}
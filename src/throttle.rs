@@ -0,0 +1,35 @@
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Under `--throttle`, the time one network operation costs: a bucket holding a single
+/// token that refills at this pace, so operations are spaced out evenly.
+static INTERVAL: OnceLock<Duration> = OnceLock::new();
+/// When the bucket is next full again.
+static NEXT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Allows at most `per_minute` network operations a minute for the rest of the run.
+pub fn configure(per_minute: u32) {
+   let _ = INTERVAL.set(Duration::from_secs(60) / per_minute.max(1));
+}
+
+/// Called before each fetch, push or other call to a remote: waits until the bucket has a
+/// token, then takes it. Does nothing without `--throttle`.
+pub fn wait() {
+   let Some(interval) = INTERVAL.get() else {
+      return;
+   };
+   let mut next = NEXT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+   let now = Instant::now();
+   if let Some(ready) = *next {
+      if ready > now {
+         let delay = ready - now;
+         say!(
+            "Waiting {:.1}s before the next network operation (--throttle)...",
+            delay.as_secs_f64()
+         );
+         thread::sleep(delay);
+      }
+   }
+   *next = Some(Instant::now() + *interval);
+}
@@ -0,0 +1,187 @@
+use anyhow::{bail, Result};
+use std::io::{stdin, IsTerminal};
+use std::process::Command;
+
+use crate::cli::Options;
+use crate::config::CONFIG_FILE;
+use crate::error::LabtohubError;
+use crate::{
+   aggregate, configured_remotes, redact_url, ref_exists, run_output, tag, validate_branch_name,
+   validate_push_ref,
+};
+
+/// `git switch` and `git restore` arrived in 2.23.
+const MIN_GIT: (u32, u32) = (2, 23);
+/// `git merge-tree --write-tree`, used for several `--source-branch`es, arrived in 2.38.
+const MIN_GIT_AGGREGATE: (u32, u32) = (2, 38);
+
+struct Checklist {
+   failed: usize,
+   total: usize,
+}
+
+impl Checklist {
+   fn check(&mut self, what: &str, result: Result<String>) -> bool {
+      self.total += 1;
+      match result {
+         Ok(detail) => {
+            say!("  [ok]   {}: {}", what, detail);
+            true
+         }
+         Err(err) => {
+            self.failed += 1;
+            say!("  [fail] {}: {}", what, err);
+            false
+         }
+      }
+   }
+}
+
+/// `(major, minor)` from `git version 2.39.5` and the like.
+fn parse_git_version(text: &str) -> Option<(u32, u32)> {
+   let version = text.split_whitespace().nth(2)?;
+   let mut parts = version.split('.');
+   Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+fn check_git(options: &Options) -> Result<String> {
+   let output = Command::new("git")
+      .arg("--version")
+      .output()
+      .map_err(|_| LabtohubError::GitNotFound)?;
+   let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+   let Some(version) = parse_git_version(&text) else {
+      bail!("could not read the version from '{}'", text);
+   };
+   let (needed, reason) = if options.extra_source_branches.is_empty() {
+      (MIN_GIT, "")
+   } else {
+      (MIN_GIT_AGGREGATE, " for several --source-branch values")
+   };
+   if version < needed {
+      bail!("{} is too old; labtohub needs git {}.{} or newer{}", text, needed.0, needed.1, reason);
+   }
+   Ok(text)
+}
+
+fn check_repository(options: &Options) -> Result<String> {
+   // A bare repository given with --git-dir has no top level to show.
+   let query = if options.git_dir.is_some() { "--absolute-git-dir" } else { "--show-toplevel" };
+   match run_output("git", &["rev-parse", query]) {
+      Ok(top) => Ok(top),
+      Err(_) => bail!("the current directory is not inside a git repository"),
+   }
+}
+
+fn check_remote(remotes: &[String], remote: &str, url: Option<&str>, flag: &str) -> Result<String> {
+   if let Some(url) = url {
+      return Ok(format!("{} (a remote is added for the run if none points there)", redact_url(url)));
+   }
+   if !remotes.iter().any(|known| known == remote) {
+      bail!("there is no '{}' remote; add it with `git remote add` or pass {}", remote, flag);
+   }
+   run_output("git", &["remote", "get-url", remote]).map(|url| redact_url(&url))
+}
+
+fn check_names(options: &Options) -> Result<String> {
+   validate_branch_name(&options.source_branch)?;
+   if let Some(tag) = &options.source_tag {
+      if run_output("git", &["check-ref-format", &options.source_remote_ref()]).is_err() {
+         bail!("'{}' is not a valid tag name (--source-ref)", tag);
+      }
+   }
+   for branch in &options.extra_source_branches {
+      validate_branch_name(branch)?;
+   }
+   validate_branch_name(&options.staging_branch())?;
+   if let Some(branch) = &options.branch {
+      validate_branch_name(branch)?;
+   }
+   validate_push_ref(options)?;
+   tag::validate(options)?;
+   Ok(format!("pushing to {}", options.push_ref()))
+}
+
+/// Mirrors what a sync asks for when it cannot prompt: a merge message.
+fn check_non_interactive(options: &Options) -> Result<String> {
+   let has_message = options.message.is_some()
+      || options.message_file.is_some()
+      || options.message_from_origin_log;
+   let blank = |message: &Option<String>| message.as_deref().is_some_and(|text| text.trim().is_empty());
+   if blank(&options.message) && !options.allow_empty_message {
+      bail!("The merge message given with -m is empty; give one, or pass --allow-empty-message");
+   }
+   if has_message {
+      return Ok("a merge message is given".to_string());
+   }
+   if options.yes {
+      bail!("--yes needs a merge message; pass it with -m, -F, --message-from-origin-log or as arguments");
+   }
+   if !stdin().is_terminal() {
+      return Ok("no merge message given, so one will be read from stdin".to_string());
+   }
+   Ok("no merge message given; a sync will prompt for one (pass -y and -m to run unattended)".to_string())
+}
+
+fn check_cached_refs(options: &Options) -> Result<String> {
+   let mut references = vec![options.source_ref(), options.dest_ref()];
+   references.extend(aggregate::extra_source_refs(options));
+   let missing = references
+      .iter()
+      .filter(|reference| !ref_exists(reference))
+      .cloned()
+      .collect::<Vec<_>>();
+   if !missing.is_empty() {
+      bail!("{} not fetched yet, which --no-fetch needs", missing.join(", "));
+   }
+   Ok(references.join(", "))
+}
+
+/// `labtohub validate`: the setup checks a sync starts with, without fetching or pushing.
+/// `config_error` is why the config could not be loaded, in which case `options` holds only
+/// the command-line options. Fails when any check does.
+pub fn run(options: &Options, config_error: Option<&anyhow::Error>) -> Result<()> {
+   say!("Validating the labtohub setup (no fetch or push)...");
+   let mut checklist = Checklist { failed: 0, total: 0 };
+   let git = checklist.check("git", check_git(options));
+   let in_repository = git && checklist.check("repository", check_repository(options));
+   checklist.check(
+      "config",
+      match config_error {
+         Some(err) => Err(anyhow::anyhow!("{:#}", err)),
+         None if std::path::Path::new(CONFIG_FILE).exists() => Ok(format!("{} parses", CONFIG_FILE)),
+         None => Ok(format!("no {}, using the defaults", CONFIG_FILE)),
+      },
+   );
+   if in_repository {
+      let remotes = configured_remotes()?;
+      checklist.check(
+         "source remote",
+         check_remote(
+            &remotes,
+            &options.source_remote,
+            options.source_url.as_deref(),
+            "--source-remote",
+         ),
+      );
+      checklist.check(
+         "destination remote",
+         check_remote(
+            &remotes,
+            &options.dest_remote,
+            options.dest_url.as_deref(),
+            "--dest-remote",
+         ),
+      );
+      checklist.check("branch names", check_names(options));
+      if options.no_fetch {
+         checklist.check("remote-tracking refs", check_cached_refs(options));
+      }
+   }
+   checklist.check("non-interactive use", check_non_interactive(options));
+   if checklist.failed > 0 {
+      bail!("{} of {} checks failed", checklist.failed, checklist.total);
+   }
+   say!("All {} checks passed.", checklist.total);
+   Ok(())
+}
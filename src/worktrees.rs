@@ -0,0 +1,168 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cli::Options;
+use crate::{lock, remove_worktree, run_output, TMP_WORKTREE, WORKTREE_OWNER_MARKER};
+
+/// One entry of `git worktree list --porcelain`.
+pub struct Worktree {
+   pub path: String,
+   /// The checked-out branch, `None` when detached.
+   pub branch: Option<String>,
+   /// The directory is gone but git still has it registered.
+   pub prunable: bool,
+}
+
+/// Every worktree of the clone, the main checkout first.
+pub fn list() -> Result<Vec<Worktree>> {
+   Ok(parse(&run_output("git", &["worktree", "list", "--porcelain"])?))
+}
+
+fn parse(listing: &str) -> Vec<Worktree> {
+   listing
+      .split("\n\n")
+      .filter_map(|block| {
+         let mut lines = block.lines();
+         let path = lines.next()?.strip_prefix("worktree ")?.to_string();
+         let rest = lines.collect::<Vec<_>>();
+         let branch = rest
+            .iter()
+            .find_map(|line| line.strip_prefix("branch "))
+            .map(|branch| branch.trim_start_matches("refs/heads/").to_string());
+         let prunable = rest.iter().any(|line| line.starts_with("prunable"));
+         Some(Worktree {
+            path,
+            branch,
+            prunable,
+         })
+      })
+      .collect()
+}
+
+/// Whether labtohub made `worktree`: it sits at a `.labtohub-tmp` path and is marked as ours,
+/// on a staging branch, or detached as the bootstrap path leaves it. A `.labtohub-tmp` whose
+/// directory is gone cannot be checked further, so its name has to do.
+fn is_labtohub(options: &Options, worktree: &Worktree) -> bool {
+   if Path::new(&worktree.path).file_name() != Some(TMP_WORKTREE.as_ref()) {
+      return false;
+   }
+   if worktree.prunable {
+      return true;
+   }
+   let marked = run_output("git", &["-C", &worktree.path, "rev-parse", "--absolute-git-dir"])
+      .is_ok_and(|git_dir| PathBuf::from(git_dir).join(WORKTREE_OWNER_MARKER).is_file());
+   marked
+      || worktree
+         .branch
+         .as_deref()
+         .is_none_or(|branch| branch.starts_with(&options.staging_prefix))
+}
+
+/// Drops git's registration of a worktree whose directory is already gone, without the
+/// repository-wide `git worktree prune` that would drop other people's as well.
+fn unregister(path: &str) -> Result<()> {
+   let common_dir = PathBuf::from(run_output("git", &["rev-parse", "--git-common-dir"])?);
+   let Ok(entries) = fs::read_dir(common_dir.join("worktrees")) else {
+      return Ok(());
+   };
+   for entry in entries.flatten() {
+      let gitdir = fs::read_to_string(entry.path().join("gitdir")).unwrap_or_default();
+      if Path::new(gitdir.trim()).parent() == Some(Path::new(path)) {
+         fs::remove_dir_all(entry.path())?;
+      }
+   }
+   Ok(())
+}
+
+/// `labtohub worktrees`: lists the clone's worktrees and which of them labtohub left behind;
+/// with `--prune`, removes those, and only those.
+pub fn run(options: &Options) -> Result<()> {
+   let worktrees = list()?;
+   say!("Worktrees of this repository:");
+   for (index, worktree) in worktrees.iter().enumerate() {
+      let branch = worktree.branch.as_deref().unwrap_or("detached");
+      let mut notes = Vec::new();
+      if index == 0 {
+         notes.push("main checkout");
+      } else if is_labtohub(options, worktree) {
+         notes.push("labtohub");
+      }
+      if worktree.prunable {
+         notes.push("directory missing");
+      }
+      let notes = if notes.is_empty() {
+         String::new()
+      } else {
+         format!(" [{}]", notes.join(", "))
+      };
+      say!("  {} ({}){}", worktree.path, branch, notes);
+   }
+
+   let ours = worktrees
+      .iter()
+      .skip(1)
+      .filter(|worktree| is_labtohub(options, worktree))
+      .collect::<Vec<_>>();
+   if !options.prune_worktrees {
+      if !ours.is_empty() {
+         say!("Run `labtohub worktrees --prune` to remove the {} labtohub worktree(s).", ours.len());
+      }
+      return Ok(());
+   }
+   if ours.is_empty() {
+      say!("No labtohub worktrees to prune.");
+      return Ok(());
+   }
+   // A running sync is using its worktree; taking the lock makes sure none is.
+   let _lock = lock::acquire(Duration::ZERO)?;
+   for worktree in &ours {
+      say!("Removing {}...", worktree.path);
+      remove_worktree(&worktree.path);
+      unregister(&worktree.path)?;
+   }
+   say!("Pruned {} labtohub worktree(s); other worktrees were left alone.", ours.len());
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn porcelain_listing_is_parsed() {
+      let listing = "worktree /src/app\nHEAD 1111111111111111111111111111111111111111\nbranch refs/heads/main\n\n\
+                     worktree /src/app/.labtohub-tmp\nHEAD 2222222222222222222222222222222222222222\ndetached\n\n\
+                     worktree /tmp/gone\nHEAD 3333333333333333333333333333333333333333\nbranch refs/heads/feature/x\n\
+                     prunable gitdir file points to non-existent location";
+      let worktrees = parse(listing);
+      assert_eq!(worktrees.len(), 3);
+      assert_eq!(worktrees[0].path, "/src/app");
+      assert_eq!(worktrees[0].branch.as_deref(), Some("main"));
+      assert!(!worktrees[0].prunable);
+      assert_eq!(worktrees[1].path, "/src/app/.labtohub-tmp");
+      assert_eq!(worktrees[1].branch, None);
+      assert_eq!(worktrees[2].branch.as_deref(), Some("feature/x"));
+      assert!(worktrees[2].prunable);
+   }
+
+   fn worktree(path: &str, branch: Option<&str>, prunable: bool) -> Worktree {
+      Worktree {
+         path: path.to_string(),
+         branch: branch.map(String::from),
+         prunable,
+      }
+   }
+
+   #[test]
+   fn only_labtohub_tmp_worktrees_of_ours_count() {
+      let options = Options::default();
+      let missing = "/nonexistent/app/.labtohub-tmp";
+      assert!(is_labtohub(&options, &worktree(missing, None, false)));
+      assert!(is_labtohub(&options, &worktree(missing, Some("labtohub-main"), false)));
+      assert!(is_labtohub(&options, &worktree(missing, Some("feature"), true)));
+      assert!(!is_labtohub(&options, &worktree(missing, Some("feature"), false)));
+      assert!(!is_labtohub(&options, &worktree("/nonexistent/app/other", None, true)));
+   }
+}
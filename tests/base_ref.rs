@@ -0,0 +1,19 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+#[test]
+fn base_ref_rebuilds_the_branch_on_an_older_commit() {
+   let sandbox = Sandbox::new("base-ref");
+   sandbox.git(&["-C", "seed", "checkout", "-q", "-b", "hub", "main~1"]);
+   sandbox.commit("hub.txt", "github only\n", "hub edit");
+   sandbox.git(&["-C", "seed", "push", "-q", "../github.git", "hub:main"]);
+
+   let run = sandbox.labtohub(&["-y", "--base-ref", "github/main~1", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   let out = stdout(&run);
+   assert!(out.contains("Building on github/main~1"), "{}", out);
+   // The github-only commit is gone: the branch was rebuilt on c1 and force pushed.
+   assert_eq!(sandbox.github_log(), "Sync docs\nc1");
+   assert_eq!(sandbox.git(&["--git-dir", "github.git", "show", "main:a.txt"]), "one\ntwo");
+}
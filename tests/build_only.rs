@@ -0,0 +1,21 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+#[test]
+fn build_only_commits_locally_and_leaves_github_alone() {
+   let sandbox = Sandbox::new("build-only");
+   let github_before = sandbox.git(&["--git-dir", "github.git", "rev-parse", "main"]);
+   let run = sandbox.labtohub(&["-y", "--build-only", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+
+   let content = sandbox.git(&["-C", "work", "rev-parse", "sync-docs"]);
+   let out = stdout(&run);
+   assert!(out.contains(&format!("Built content commit {} on branch 'sync-docs'", content)), "{}", out);
+   assert_eq!(sandbox.git(&["-C", "work", "log", "-1", "--format=%s", "sync-docs"]), "Sync docs");
+   assert_eq!(sandbox.git(&["-C", "work", "show", "sync-docs:a.txt"]), "one\ntwo");
+   // Neither merged into the staging branch nor pushed.
+   assert_eq!(sandbox.git(&["-C", "work", "rev-parse", "labtohub-main"]), github_before);
+   assert_eq!(sandbox.git(&["--git-dir", "github.git", "rev-parse", "main"]), github_before);
+   assert!(sandbox.root.join("work/.labtohub-tmp").is_dir());
+}
@@ -0,0 +1,13 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn failing_pre_commit_hook_fails_the_sync() {
+   let sandbox = Sandbox::new("commit-hook");
+   sandbox.hook("work/.git/hooks/pre-commit", "#!/bin/sh\necho hook says no >&2\nexit 1\n");
+   let run = sandbox.labtohub(&["-y", "-m", "Sync docs"]);
+   assert!(!run.status.success());
+   assert!(String::from_utf8_lossy(&run.stderr).contains("hook says no"));
+   assert_eq!(sandbox.github_log(), "c1");
+}
@@ -0,0 +1,94 @@
+// Shared by the end-to-end tests; each test file uses only some of it.
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// A scratch origin and github, both bare, and a clone of origin with github added.
+pub struct Sandbox {
+   pub root: PathBuf,
+}
+
+impl Sandbox {
+   pub fn new(name: &str) -> Self {
+      let root = std::env::temp_dir().join(format!("labtohub-{}-{}", name, std::process::id()));
+      let _ = fs::remove_dir_all(&root);
+      fs::create_dir_all(&root).unwrap();
+      let sandbox = Sandbox { root };
+      sandbox.git(&["init", "-q", "--bare", "-b", "main", "origin.git"]);
+      sandbox.git(&["init", "-q", "--bare", "-b", "main", "github.git"]);
+      sandbox.git(&["init", "-q", "-b", "main", "seed"]);
+      sandbox.commit("a.txt", "one\n", "c1");
+      sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+      sandbox.git(&["-C", "seed", "push", "-q", "../github.git", "main"]);
+      sandbox.commit("a.txt", "one\ntwo\n", "c2");
+      sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+      sandbox.git(&["clone", "-q", "origin.git", "work"]);
+      // Absolute, since labtohub pushes from its worktree inside `work`.
+      let github = sandbox.root.join("github.git");
+      sandbox.git(&["-C", "work", "remote", "add", "github", github.to_str().unwrap()]);
+      sandbox
+   }
+
+   pub fn command(&self, program: &str) -> Command {
+      let mut command = Command::new(program);
+      command
+         .current_dir(&self.root)
+         .env("GIT_AUTHOR_NAME", "A")
+         .env("GIT_AUTHOR_EMAIL", "a@example.com")
+         .env("GIT_COMMITTER_NAME", "A")
+         .env("GIT_COMMITTER_EMAIL", "a@example.com")
+         .env("TERM", "dumb");
+      command
+   }
+
+   pub fn git(&self, args: &[&str]) -> String {
+      let output = self.command("git").args(args).output().unwrap();
+      assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+      String::from_utf8_lossy(&output.stdout).trim().to_string()
+   }
+
+   pub fn commit(&self, path: &str, contents: &str, message: &str) {
+      fs::write(self.root.join("seed").join(path), contents).unwrap();
+      self.git(&["-C", "seed", "add", path]);
+      self.git(&["-C", "seed", "commit", "-q", "-m", message]);
+   }
+
+   /// Installs `script` as the executable hook at `path`, relative to the sandbox.
+   pub fn hook(&self, path: &str, script: &str) {
+      let hook = self.root.join(path);
+      fs::write(&hook, script).unwrap();
+      fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+   }
+
+   /// Makes github refuse the next push, as a dropped connection would.
+   pub fn fail_next_push(&self) {
+      self.hook("github.git/hooks/pre-receive", "#!/bin/sh\nrm \"$0\"\necho simulated outage >&2\nexit 1\n");
+   }
+
+   pub fn labtohub(&self, args: &[&str]) -> Output {
+      let mut command = self.command(env!("CARGO_BIN_EXE_labtohub"));
+      command.current_dir(self.root.join("work")).args(args);
+      command.output().unwrap()
+   }
+
+   pub fn state(&self) -> Option<String> {
+      fs::read_to_string(self.root.join("work/.git/labtohub-state")).ok()
+   }
+
+   pub fn github_log(&self) -> String {
+      self.git(&["--git-dir", "github.git", "log", "--format=%s", "main"])
+   }
+}
+
+impl Drop for Sandbox {
+   fn drop(&mut self) {
+      let _ = fs::remove_dir_all(&self.root);
+   }
+}
+
+pub fn stdout(output: &Output) -> String {
+   String::from_utf8_lossy(&output.stdout).to_string()
+}
@@ -0,0 +1,21 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+#[test]
+fn sync_under_the_threshold_skips_the_prompt() {
+   let sandbox = Sandbox::new("confirm-under");
+   let run = sandbox.labtohub(&["--confirm-diff-threshold", "5", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert!(stdout(&run).contains("1 file(s) differ, fewer than --confirm-diff-threshold 5"), "{}", stdout(&run));
+   assert_eq!(sandbox.github_log(), "Sync docs\nc1");
+}
+
+#[test]
+fn sync_at_the_threshold_keeps_the_prompt() {
+   let sandbox = Sandbox::new("confirm-at");
+   // Without a terminal the prompt is passed over either way, so only the decision shows.
+   let run = sandbox.labtohub(&["--confirm-diff-threshold", "1", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert!(!stdout(&run).contains("skipping the final prompt"), "{}", stdout(&run));
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+#[test]
+fn missing_branch_beside_others_needs_create_dest_branch() {
+   let sandbox = Sandbox::new("create-dest-refused");
+   let run = sandbox.labtohub(&["-y", "--dest-branch", "mirror", "-m", "Sync docs"]);
+   assert!(!run.status.success());
+   let stderr = String::from_utf8_lossy(&run.stderr);
+   assert!(stderr.contains("has other branches but no 'mirror'"), "{}", stderr);
+   let branches = sandbox.git(&["--git-dir", "github.git", "branch", "--format=%(refname:short)"]);
+   assert_eq!(branches, "main");
+}
+
+#[test]
+fn create_dest_branch_creates_it_from_origin() {
+   let sandbox = Sandbox::new("create-dest");
+   let run = sandbox.labtohub(&["-y", "--dest-branch", "mirror", "--create-dest-branch", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert!(stdout(&run).contains("creating it from origin/main (--create-dest-branch)"), "{}", stdout(&run));
+   assert_eq!(sandbox.git(&["--git-dir", "github.git", "show", "mirror:a.txt"]), "one\ntwo");
+   assert_eq!(sandbox.github_log(), "c1");
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::Sandbox;
+
+/// `files` committed on top of the sandbox's main and pushed to both remotes as the common
+/// base, then `origin` committed for origin and `github` for github on top of it.
+fn diverged(name: &str, files: &[(&str, &str, &str, &str)]) -> Sandbox {
+   let sandbox = Sandbox::new(name);
+   for (path, base, _, _) in files {
+      sandbox.commit(path, base, "base");
+   }
+   sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+   sandbox.git(&["-C", "seed", "push", "-q", "../github.git", "main"]);
+   sandbox.git(&["-C", "seed", "checkout", "-q", "-b", "hub"]);
+   for (path, _, _, github) in files {
+      sandbox.commit(path, github, "hub edit");
+   }
+   sandbox.git(&["-C", "seed", "push", "-q", "../github.git", "hub:main"]);
+   sandbox.git(&["-C", "seed", "checkout", "-q", "main"]);
+   for (path, _, origin, _) in files {
+      sandbox.commit(path, origin, "origin edit");
+   }
+   sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+   sandbox
+}
+
+fn github_file(sandbox: &Sandbox, path: &str) -> String {
+   sandbox.git(&["--git-dir", "github.git", "show", &format!("main:{}", path)])
+}
+
+#[test]
+fn divergent_changelog_keeps_both_sides() {
+   let sandbox = diverged(
+      "merge-changelog",
+      &[(
+         "CHANGELOG.md",
+         "# Changes\n\n- one\n",
+         "# Changes\n\n- two\n- one\n",
+         "# Changes\n\n- one\n\nSee the wiki.\n",
+      )],
+   );
+   let run = sandbox.labtohub(&["-y", "--merge-path", "CHANGELOG.md", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert_eq!(github_file(&sandbox, "CHANGELOG.md"), "# Changes\n\n- two\n- one\n\nSee the wiki.");
+}
+
+#[test]
+fn merge_path_leaves_files_outside_include_alone() {
+   let sandbox = diverged(
+      "merge-include",
+      &[
+         ("docs.md", "a\nb\nc\nd\n", "A\nb\nc\nd\n", "a\nb\nc\nD\n"),
+         ("README.md", "w\nx\ny\nz\n", "W\nx\ny\nz\n", "w\nx\ny\nZ\n"),
+      ],
+   );
+   let run = sandbox.labtohub(&["-y", "--include", "docs.md", "--merge-path", "*.md", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert_eq!(github_file(&sandbox, "docs.md"), "A\nb\nc\nD");
+   assert_eq!(github_file(&sandbox, "README.md"), "w\nx\ny\nZ");
+}
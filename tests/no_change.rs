@@ -0,0 +1,32 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+/// github with a commit of its own that holds exactly origin's files.
+fn same_tree(name: &str) -> Sandbox {
+   let sandbox = Sandbox::new(name);
+   sandbox.git(&["-C", "seed", "checkout", "-q", "-b", "hub", "main~1"]);
+   sandbox.commit("a.txt", "one\ntwo\n", "hub copy");
+   sandbox.git(&["-C", "seed", "push", "-q", "../github.git", "hub:main"]);
+   sandbox
+}
+
+#[test]
+fn matching_trees_skip_the_build() {
+   let sandbox = same_tree("no-change-trees");
+   let run = sandbox.labtohub(&["-y", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   let out = stdout(&run);
+   assert!(out.contains("github/main already has the contents of origin/main"), "{}", out);
+   assert!(!out.contains("Adding temporary worktree"), "{}", out);
+   assert_eq!(sandbox.github_log(), "hub copy\nc1");
+}
+
+#[test]
+fn commit_empty_builds_even_when_trees_match() {
+   let sandbox = same_tree("no-change-empty");
+   let run = sandbox.labtohub(&["-y", "--commit-empty", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert!(!stdout(&run).contains("already has the contents"), "{}", stdout(&run));
+   assert_eq!(sandbox.github_log(), "Sync docs\nhub copy\nc1");
+}
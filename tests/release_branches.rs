@@ -0,0 +1,24 @@
+mod common;
+
+use common::Sandbox;
+
+#[test]
+fn each_tag_gets_its_own_release_branch() {
+   let sandbox = Sandbox::new("release-branches");
+   sandbox.git(&["-C", "seed", "tag", "v1", "main~1"]);
+   sandbox.git(&["-C", "seed", "tag", "v2", "main"]);
+   sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "v1", "v2"]);
+
+   for tag in ["v1", "v2"] {
+      let message = format!("Release {}", tag);
+      let run = sandbox.labtohub(&["-y", "--source-ref", tag, "--dest-branch-per-tag", "-m", &message]);
+      assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   }
+   let show = |rev: &str| sandbox.git(&["--git-dir", "github.git", "show", rev]);
+   assert_eq!(show("release/v1:a.txt"), "one");
+   assert_eq!(show("release/v2:a.txt"), "one\ntwo");
+   let subject = |branch: &str| sandbox.git(&["--git-dir", "github.git", "log", "-1", "--format=%s", branch]);
+   assert_eq!(subject("release/v1"), "Release v1");
+   assert_eq!(subject("release/v2"), "Release v2");
+   assert_eq!(sandbox.github_log(), "c1");
+}
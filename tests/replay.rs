@@ -0,0 +1,37 @@
+mod common;
+
+use common::Sandbox;
+
+/// Origin three commits ahead of github: c2 from the sandbox, then c3 and c4.
+fn three_ahead(name: &str) -> Sandbox {
+   let sandbox = Sandbox::new(name);
+   sandbox.commit("b.txt", "b\n", "c3");
+   sandbox.commit("a.txt", "one\ntwo\nthree\n", "c4");
+   sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+   sandbox
+}
+
+#[test]
+fn replay_publishes_each_origin_commit() {
+   let sandbox = three_ahead("replay-range");
+   let run = sandbox.labtohub(&["-y", "--replay", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert_eq!(sandbox.github_log(), "c4\nc3\nc2\nc1");
+   let changed = sandbox.git(&["--git-dir", "github.git", "show", "--format=", "--name-only", "main~1"]);
+   assert_eq!(changed, "b.txt");
+}
+
+#[test]
+fn replay_stops_before_pushing_when_a_commit_conflicts() {
+   let sandbox = three_ahead("replay-conflict");
+   // github edits the line c2 changes, so c2 no longer applies there.
+   sandbox.git(&["-C", "seed", "checkout", "-q", "-b", "hub", "main~3"]);
+   sandbox.commit("a.txt", "ONE\n", "hub edit");
+   sandbox.git(&["-C", "seed", "push", "-q", "../github.git", "hub:main"]);
+
+   let run = sandbox.labtohub(&["-y", "--replay", "-m", "Sync docs"]);
+   assert!(!run.status.success());
+   let stderr = String::from_utf8_lossy(&run.stderr);
+   assert!(stderr.contains("('c2') does not apply cleanly"), "{}", stderr);
+   assert_eq!(sandbox.github_log(), "hub edit\nc1");
+}
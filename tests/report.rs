@@ -0,0 +1,35 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+use std::process::Output;
+
+/// Runs a sync with `--report-file` in the sandbox and returns it with the report's text.
+fn reported(sandbox: &Sandbox) -> (Output, String) {
+   let report = sandbox.root.join("report.json");
+   let path = report.to_str().unwrap();
+   let run = sandbox.labtohub(&["-y", "--format", "json", "--report-file", path, "-m", "Sync docs"]);
+   (run, fs::read_to_string(&report).expect("the report is written"))
+}
+
+#[test]
+fn report_records_a_successful_run() {
+   let sandbox = Sandbox::new("report-ok");
+   let (run, report) = reported(&sandbox);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   assert!(report.starts_with("{\"result\":\"synced\""), "{}", report);
+   assert!(report.contains("\"outcome\":{\"result\":\"synced\",\"branch\":\"sync-docs\""), "{}", report);
+   assert!(report.contains("{\"phase\":\"push\""), "{}", report);
+   assert!(report.contains("\"changed_files\":[\"M\\ta.txt\"]"), "{}", report);
+}
+
+#[test]
+fn report_records_a_failed_run() {
+   let sandbox = Sandbox::new("report-failed");
+   sandbox.fail_next_push();
+   let (run, report) = reported(&sandbox);
+   assert!(!run.status.success());
+   assert!(report.starts_with("{\"result\":\"error\",\"failed_phase\":\"push\""), "{}", report);
+   assert!(report.contains("\"error\":\"Command failed: git"), "{}", report);
+   assert!(report.contains("\"outcome\":{}"), "{}", report);
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+#[test]
+fn resume_pushes_again_after_a_failed_push() {
+   let sandbox = Sandbox::new("resume-push");
+   sandbox.fail_next_push();
+   let failed = sandbox.labtohub(&["-y", "-m", "Sync docs"]);
+   assert!(String::from_utf8_lossy(&failed.stderr).contains("simulated outage"));
+   let state = sandbox.state().expect("the failed push is recorded");
+   assert!(state.contains("\nphase merged\n"), "{}", state);
+   assert_eq!(sandbox.github_log(), "c1");
+
+   let resumed = sandbox.labtohub(&["-y", "--resume", "-m", "Sync docs"]);
+   assert!(resumed.status.success(), "{}", String::from_utf8_lossy(&resumed.stderr));
+   let out = stdout(&resumed);
+   assert!(out.contains("Resuming the interrupted sync: pushing staging branch"), "{}", out);
+   assert!(!out.contains("Creating branch"), "{}", out);
+   assert_eq!(sandbox.github_log(), "Sync docs\nc1");
+   assert!(sandbox.state().is_none());
+}
+
+#[test]
+fn resume_starts_fresh_when_origin_moved() {
+   let sandbox = Sandbox::new("resume-moved");
+   sandbox.fail_next_push();
+   assert!(!sandbox.labtohub(&["-y", "-m", "Sync docs"]).status.success());
+   sandbox.commit("b.txt", "new\n", "c3");
+   sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+
+   let resumed = sandbox.labtohub(&["-y", "--resume", "-m", "Sync docs"]);
+   assert!(resumed.status.success(), "{}", String::from_utf8_lossy(&resumed.stderr));
+   let out = stdout(&resumed);
+   assert!(out.contains("no longer matches origin"), "{}", out);
+   assert_eq!(sandbox.git(&["--git-dir", "github.git", "show", "main:b.txt"]), "new");
+}
+
+#[test]
+fn orphan_snapshot_without_changes_leaves_no_state() {
+   let sandbox = Sandbox::new("resume-orphan");
+   let first = sandbox.labtohub(&["-y", "--orphan-each-sync", "-m", "Snapshot"]);
+   assert!(first.status.success(), "{}", String::from_utf8_lossy(&first.stderr));
+
+   let again = sandbox.labtohub(&["-y", "--orphan-each-sync", "-m", "Snapshot"]);
+   assert!(again.status.success(), "{}", String::from_utf8_lossy(&again.stderr));
+   assert!(stdout(&again).contains("already a single-commit snapshot"), "{}", stdout(&again));
+   assert!(sandbox.state().is_none(), "{:?}", sandbox.state());
+   assert_eq!(sandbox.github_log(), "Snapshot");
+}
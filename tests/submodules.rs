@@ -0,0 +1,39 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+/// Makes `name.git`, a bare repository holding one commit with `file`, built in `name`, and
+/// records `submodules` (`(url, path)`) in that commit first.
+fn repository(sandbox: &Sandbox, name: &str, file: &str, submodules: &[(&str, &str)]) {
+   sandbox.git(&["init", "-q", "-b", "main", name]);
+   fs::write(sandbox.root.join(name).join(file), format!("{}\n", name)).unwrap();
+   sandbox.git(&["-C", name, "add", file]);
+   for (url, path) in submodules {
+      sandbox.git(&["-C", name, "-c", "protocol.file.allow=always", "submodule", "add", "-q", url, path]);
+   }
+   sandbox.git(&["-C", name, "commit", "-q", "-m", name]);
+   sandbox.git(&["clone", "-q", "--bare", name, &format!("{}.git", name)]);
+}
+
+#[test]
+fn nested_submodules_are_pushed_as_regular_files() {
+   let sandbox = Sandbox::new("submodules");
+   repository(&sandbox, "inner", "inner.txt", &[]);
+   repository(&sandbox, "mid", "mid.txt", &[("../inner.git", "lib/inner")]);
+   sandbox.git(&[
+      "-C", "seed", "-c", "protocol.file.allow=always", "submodule", "add", "-q", "../mid.git", "vendor/mid",
+   ]);
+   sandbox.git(&["-C", "seed", "commit", "-q", "-m", "c3"]);
+   sandbox.git(&["-C", "seed", "push", "-q", "../origin.git", "main"]);
+
+   let run = sandbox.labtohub(&["-y", "--vendor-submodules", "-m", "Sync docs"]);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+   let tree = sandbox.git(&["--git-dir", "github.git", "ls-tree", "-r", "main"]);
+   assert!(!tree.contains(".gitmodules"), "{}", tree);
+   assert!(!tree.contains("160000"), "{}", tree);
+   let show = |path: &str| sandbox.git(&["--git-dir", "github.git", "show", &format!("main:{}", path)]);
+   assert_eq!(show("vendor/mid/mid.txt"), "mid");
+   assert_eq!(show("vendor/mid/lib/inner/inner.txt"), "inner");
+   assert_eq!(show("a.txt"), "one\ntwo");
+}
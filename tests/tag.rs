@@ -0,0 +1,35 @@
+mod common;
+
+use common::Sandbox;
+use std::fs;
+
+#[test]
+fn signed_tag_carries_the_rendered_template() {
+   let sandbox = Sandbox::new("tag-signed");
+   let key = sandbox.root.join("key");
+   sandbox.git(&["-C", "work", "config", "gpg.format", "ssh"]);
+   let status = sandbox
+      .command("ssh-keygen")
+      .args(["-q", "-t", "ed25519", "-N", "", "-C", "a@example.com", "-f"])
+      .arg(&key)
+      .status()
+      .unwrap();
+   assert!(status.success());
+   let public = key.with_extension("pub");
+   sandbox.git(&["-C", "work", "config", "user.signingkey", public.to_str().unwrap()]);
+   let signers = sandbox.root.join("allowed_signers");
+   fs::write(&signers, format!("a@example.com {}", fs::read_to_string(&public).unwrap())).unwrap();
+
+   let template = "Mirror of {source_sha}: {subject}";
+   let args = ["-y", "--sign", "--tag", "v1", "--tag-message-template", template, "-m", "Sync docs"];
+   let run = sandbox.labtohub(&args);
+   assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+
+   let source_sha = sandbox.git(&["--git-dir", "origin.git", "rev-parse", "main"]);
+   let message = sandbox.git(&["--git-dir", "github.git", "tag", "-l", "--format=%(contents:subject)", "v1"]);
+   assert_eq!(message, format!("Mirror of {}: Sync docs", source_sha));
+   let tag = sandbox.git(&["--git-dir", "github.git", "cat-file", "tag", "v1"]);
+   assert!(tag.contains("-----BEGIN SSH SIGNATURE-----"), "{}", tag);
+   let allowed = format!("gpg.ssh.allowedSignersFile={}", signers.display());
+   sandbox.git(&["--git-dir", "github.git", "-c", "gpg.format=ssh", "-c", &allowed, "tag", "-v", "v1"]);
+}
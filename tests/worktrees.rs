@@ -0,0 +1,23 @@
+mod common;
+
+use common::{stdout, Sandbox};
+
+#[test]
+fn prune_removes_only_labtohub_worktrees() {
+   let sandbox = Sandbox::new("worktrees-prune");
+   let built = sandbox.labtohub(&["-y", "--build-only", "-m", "Sync docs"]);
+   assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+   sandbox.git(&["-C", "work", "worktree", "add", "-q", "-b", "feature", "../other"]);
+   // Named like labtohub's, but someone's own branch.
+   sandbox.git(&["-C", "work", "worktree", "add", "-q", "-b", "mine", "../elsewhere/.labtohub-tmp"]);
+
+   let pruned = sandbox.labtohub(&["worktrees", "--prune"]);
+   assert!(pruned.status.success(), "{}", String::from_utf8_lossy(&pruned.stderr));
+   assert!(stdout(&pruned).contains("Pruned 1 labtohub worktree(s)"), "{}", stdout(&pruned));
+   let listing = sandbox.git(&["-C", "work", "worktree", "list", "--porcelain"]);
+   assert!(!listing.contains("work/.labtohub-tmp"), "{}", listing);
+   assert!(listing.contains("/other\n"), "{}", listing);
+   assert!(listing.contains("/elsewhere/.labtohub-tmp\n"), "{}", listing);
+   assert!(sandbox.root.join("other").is_dir());
+   assert!(!sandbox.root.join("work/.labtohub-tmp").exists());
+}